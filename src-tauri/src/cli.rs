@@ -1,30 +1,874 @@
+//! Command line interface
+//!
+//! Without a subcommand the app opens the GUI; `run` and the other
+//! subcommands work without it. A leading `cli` from older versions is still
+//! accepted and means `run` when nothing follows it.
+
 use anyhow::Result;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use tracing::info;
 
-use crate::config::Config;
-use crate::macro_engine::MacroEngine;
+use crate::config::{ActionType, Config};
+use crate::daemon;
+use crate::diff;
+use crate::duration;
+use crate::format::ConfigFormat;
+use crate::game_keybinds::{self, GameKeybinds};
+use crate::ipc::{self, LocalSocket, Transport};
+use crate::keys;
+use crate::macro_engine::{MacroEngine, MacroEngineState};
+use crate::overrides::Overrides;
+use crate::paths::ConfigLocation;
+use crate::profiles::{ProfileStore, DEFAULT_PROFILE};
+use crate::schema;
+use crate::share;
+use crate::storage;
+use crate::templates;
+use crate::validation::ValidationReport;
+use crate::watcher::ConfigWatcher;
+
+/// The command ran successfully
+pub const EXIT_OK: i32 = 0;
+/// The command failed, e.g. a file couldn't be read or the engine couldn't start
+pub const EXIT_FAILURE: i32 = 1;
+/// The arguments were invalid (reported by the argument parser)
+pub const EXIT_USAGE: i32 = 2;
+/// `validate` or `lint` found errors in the config
+pub const EXIT_INVALID_CONFIG: i32 = 3;
+/// `lint` found warnings but no errors
+pub const EXIT_LINT_WARNINGS: i32 = 4;
+
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  success
+  1  the command failed
+  2  invalid arguments
+  3  validate or lint found errors in the config
+  4  lint found warnings
+
+Run without a command to open the GUI.";
+
+/// POE2 Macro Buddy: timed key presses and clicks with per-macro hotkeys
+#[derive(Debug, Parser)]
+#[command(name = "poe2-macro-buddy", version, after_help = EXIT_CODES_HELP)]
+pub struct Cli {
+    /// Config file to use instead of the usual locations
+    #[arg(short, long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Profile to use instead of the active one; `run` also switches to it
+    #[arg(short, long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Override one config field for this run, e.g. `macros.life_flask.interval_ms=900`
+    /// (repeatable; `POE2MB_*` environment variables do the same)
+    #[arg(long = "set", global = true, value_name = "PATH=VALUE")]
+    pub set: Vec<String>,
+
+    /// How much to log (to stderr)
+    #[arg(long, global = true, value_enum, default_value_t = LogLevel::Info)]
+    pub log_level: LogLevel,
+
+    /// Don't press keys or write files; log what would happen instead
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for tracing::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => tracing::Level::ERROR,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Trace => tracing::Level::TRACE,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the macros without the GUI; toggle them with their hotkeys
+    Run,
+
+    /// Run the macros like `run` and take commands from `ctl` and other tools on a local socket
+    Daemon {
+        /// Socket (or named pipe on Windows) to listen on
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+
+    /// Send a command to a running daemon and print its answer
+    Ctl {
+        #[arg(value_enum)]
+        method: CtlMethod,
+        /// Macro id for toggle, enable and disable; profile name for switch-profile
+        target: Option<String>,
+        /// Socket (or named pipe on Windows) the daemon listens on
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+
+    /// Check a profile for errors
+    Validate,
+
+    /// Check a profile for errors and warnings, including clashes with the game's keybinds
+    Lint,
+
+    /// Write a new config file from a template, with a JSON Schema for editors
+    Init {
+        /// Template to start from (see `templates`)
+        #[arg(long, default_value = templates::DEFAULT_TEMPLATE)]
+        template: String,
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+        /// Schema file or URL to reference instead of writing one next to the config
+        #[arg(long, value_name = "REF", conflicts_with = "no_schema")]
+        schema: Option<String>,
+        /// Don't reference a schema
+        #[arg(long)]
+        no_schema: bool,
+    },
+
+    /// List the keys, hotkeys, mouse buttons and game actions macros can use
+    ListKeys,
+
+    /// Print when each macro would fire, as if all were enabled, without pressing anything
+    Simulate {
+        /// How far ahead to simulate, e.g. `30s` or `2m`
+        #[arg(long, default_value = "30s", value_parser = duration::parse_millis)]
+        duration: u64,
+    },
+
+    /// List, switch, share and inspect profiles
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+
+    /// List the built-in templates, print one, or create a profile from it
+    Templates {
+        /// Template to print
+        name: Option<String>,
+        /// Create a profile with this name from the template instead of printing it
+        #[arg(long = "as", value_name = "PROFILE", requires = "name")]
+        as_profile: Option<String>,
+    },
+
+    /// Show which macros and settings differ between two profiles, files or share codes
+    ///
+    /// With one argument the profile is compared with it.
+    Diff {
+        /// `[<before>] <after>`: profile names, config file paths or share codes
+        #[arg(num_args = 1..=2, required = true, value_name = "SOURCE")]
+        sources: Vec<String>,
+    },
+
+    /// Rewrite a config file in the format of the output extension (.yaml, .toml or .json)
+    Convert {
+        input: PathBuf,
+        output: PathBuf,
+        /// Overwrite an existing output file
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Show the game's keybinds and how the profile's hotkeys and keys relate to them
+    Keybinds {
+        /// Game config file, if not the configured or usual one
+        path: Option<String>,
+    },
+
+    /// Print the config JSON Schema, or write it to a file
+    Schema {
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Requests `ctl` can send, named like the daemon's methods
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CtlMethod {
+    Status,
+    Toggle,
+    Enable,
+    Disable,
+    Pause,
+    Resume,
+    SwitchProfile,
+    CycleProfile,
+    Reload,
+    Stop,
+}
+
+impl CtlMethod {
+    /// JSON-RPC method name and parameters for `target`
+    fn request(self, target: Option<String>) -> Result<(&'static str, serde_json::Value)> {
+        let (method, target_key) = match self {
+            Self::Status => ("status", None),
+            Self::Toggle => ("toggle", Some(("id", true))),
+            Self::Enable => ("enable", Some(("id", false))),
+            Self::Disable => ("disable", Some(("id", false))),
+            Self::Pause => ("pause", None),
+            Self::Resume => ("resume", None),
+            Self::SwitchProfile => ("switch_profile", Some(("name", true))),
+            Self::CycleProfile => ("cycle_profile", None),
+            Self::Reload => ("reload", None),
+            Self::Stop => ("stop", None),
+        };
+        let params = match (target_key, target) {
+            (Some((key, _)), Some(target)) => serde_json::json!({ key: target }),
+            (Some((_, true)), None) => {
+                return Err(anyhow::anyhow!(
+                    "{} needs a macro id or profile name",
+                    method
+                ));
+            }
+            (None, Some(target)) => {
+                return Err(anyhow::anyhow!(
+                    "{} takes no target, got '{}'",
+                    method,
+                    target
+                ));
+            }
+            _ => serde_json::Value::Null,
+        };
+        Ok((method, params))
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileCommand {
+    /// List the profiles, marking the active one
+    List,
+
+    /// Make a profile the active one
+    Switch { name: String },
+
+    /// Print the share code of a profile
+    Export {
+        /// Profile to export (the --profile or active one by default)
+        name: Option<String>,
+    },
+
+    /// Show what a share code contains, importing it with --as
+    Import {
+        code: String,
+        /// Name of the profile to import it as
+        #[arg(long = "as", value_name = "PROFILE")]
+        as_profile: Option<String>,
+        /// Overwrite an existing profile of that name
+        #[arg(long)]
+        replace: bool,
+    },
+
+    /// Print a profile merged with everything it extends
+    Resolve {
+        /// Profile to resolve (the --profile or active one by default)
+        name: Option<String>,
+    },
+}
+
+impl Cli {
+    /// Parse the command line, accepting the `cli` prefix of older versions
+    pub fn parse_args() -> Self {
+        let mut args: Vec<OsString> = env::args_os().collect();
+        let legacy = args.get(1).is_some_and(|arg| arg == "cli");
+        if legacy {
+            args.remove(1);
+        }
+
+        let mut cli = Self::try_parse_from(args).unwrap_or_else(|e| {
+            let _ = e.print();
+            let code = if e.use_stderr() { EXIT_USAGE } else { EXIT_OK };
+            std::process::exit(code)
+        });
+        if legacy && cli.command.is_none() {
+            cli.command = Some(Command::Run);
+        }
+        cli
+    }
+}
+
+/// Validation findings that decide the exit code of `validate` and `lint`
+#[derive(Debug)]
+enum Findings {
+    Errors(usize),
+    Warnings(usize),
+}
+
+impl fmt::Display for Findings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Errors(count) => write!(f, "{} error(s) found", count),
+            Self::Warnings(count) => write!(f, "{} warning(s) found", count),
+        }
+    }
+}
+
+impl std::error::Error for Findings {}
+
+/// Exit code for a command that failed with `error`
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    match error.downcast_ref::<Findings>() {
+        Some(Findings::Errors(_)) => EXIT_INVALID_CONFIG,
+        Some(Findings::Warnings(_)) => EXIT_LINT_WARNINGS,
+        None => EXIT_FAILURE,
+    }
+}
+
+/// What every command needs besides its own arguments
+struct Context {
+    location: ConfigLocation,
+    overrides: Overrides,
+    profile: Option<String>,
+    dry_run: bool,
+}
+
+impl Context {
+    fn profiles(&self) -> ProfileStore {
+        ProfileStore::open(&self.location.path)
+    }
+
+    /// Profile given with `--profile`, else the active one
+    fn profile_name(&self, profiles: &ProfileStore) -> String {
+        self.profile.clone().unwrap_or_else(|| profiles.active())
+    }
+
+    /// Load a profile to use it, only reading it under `--dry-run`
+    fn load_profile(&self, profiles: &ProfileStore, name: &str) -> Result<Config> {
+        if self.dry_run {
+            profiles.load_read_only(name)
+        } else {
+            profiles.load(name)
+        }
+    }
+
+    /// Whether to skip `action` because of `--dry-run`, saying so if it is skipped
+    fn skip(&self, action: impl fmt::Display) -> bool {
+        if self.dry_run {
+            println!("Dry run: would {}", action);
+        }
+        self.dry_run
+    }
+}
+
+/// Run a CLI command; `cli.command` must be set
+pub async fn run_cli(cli: Cli, location: ConfigLocation, overrides: Overrides) -> Result<()> {
+    let ctx = Context {
+        location,
+        overrides,
+        profile: cli.profile,
+        dry_run: cli.dry_run,
+    };
 
-/// Run the CLI version of the macro tool
-pub async fn run_cli() -> Result<()> {
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run_engine(ctx, None).await,
+        Command::Daemon { socket } => {
+            run_engine(ctx, Some(socket.unwrap_or_else(ipc::default_endpoint))).await
+        }
+        Command::Ctl {
+            method,
+            target,
+            socket,
+        } => control(method, target, socket).await,
+        Command::Validate => validate_profile(&ctx, false),
+        Command::Lint => validate_profile(&ctx, true),
+        Command::Init {
+            template,
+            force,
+            schema,
+            no_schema,
+        } => init_config(&ctx, &template, force, schema, no_schema),
+        Command::ListKeys => {
+            list_keys();
+            Ok(())
+        }
+        Command::Simulate { duration } => simulate(&ctx, duration),
+        Command::Profile { command } => profile_command(&ctx, command),
+        Command::Templates { name, as_profile } => {
+            templates_command(&ctx, name.as_deref(), as_profile)
+        }
+        Command::Diff { sources } => diff_configs(&ctx, &sources),
+        Command::Convert {
+            input,
+            output,
+            force,
+        } => convert_config(&ctx, &input, &output, force),
+        Command::Keybinds { path } => show_keybinds(&ctx, path),
+        Command::Schema { output } => write_schema(&ctx, output),
+    }
+}
+
+/// `run` and `daemon`: run the macros until the session limit stops the engine
+///
+/// With a `control` endpoint the engine also answers the daemon protocol
+/// there, and stops on Ctrl+C so the socket is cleaned up.
+async fn run_engine(ctx: Context, control: Option<PathBuf>) -> Result<()> {
     info!("POE2 Macro Buddy - CLI Mode");
-    info!("Tip: Run without 'cli' argument to launch the GUI");
+    info!("Tip: Run without a command to launch the GUI");
+
+    let profiles = ctx.profiles();
+    let config = match &ctx.profile {
+        Some(name) if !ctx.skip(format_args!("switch to profile '{}'", name)) => {
+            profiles.set_active(name)?
+        }
+        Some(name) => {
+            // Run the chosen profile and watch its file without remembering it
+            let config = ctx.load_profile(&profiles, name)?;
+            profiles.select(name);
+            config
+        }
+        None => ctx.load_profile(&profiles, &profiles.active())?,
+    };
+
+    info!(
+        "Configuration loaded from: {} (profile '{}')",
+        profiles.active_path().display(),
+        ctx.profile_name(&profiles)
+    );
+    if ctx.dry_run {
+        info!("Dry run: macros log their presses instead of sending them");
+    }
 
-    // Load or create default configuration
-    let config_path = "config.yaml";
-    let config = Config::load_or_default(config_path)?;
+    // Create and run macro engine, hot-reloading edits to the config file
+    let mut engine = MacroEngine::new(config, profiles, ctx.overrides, ctx.dry_run);
+    let _watcher = ConfigWatcher::start(engine.state().clone(), |_| {})?;
+    let Some(endpoint) = control else {
+        return engine.run().await;
+    };
 
-    info!("Configuration loaded from: {}", config_path);
+    // Bind before starting so a second daemon fails without pressing anything
+    let listener = LocalSocket::bind(&endpoint)
+        .map_err(|e| anyhow::anyhow!("Cannot listen on {}: {}", endpoint.display(), e))?;
+    info!("Listening for control commands on {}", endpoint.display());
+    engine.start().await?;
+    let state = engine.state().clone();
+    tokio::select! {
+        result = daemon::serve(listener, state.clone()) => result?,
+        _ = tokio::signal::ctrl_c() => state.stop().await,
+    }
+    Ok(())
+}
+
+/// `ctl`: send one request to a running daemon
+async fn control(method: CtlMethod, target: Option<String>, socket: Option<PathBuf>) -> Result<()> {
+    let endpoint = socket.unwrap_or_else(ipc::default_endpoint);
+    let (method, params) = method.request(target)?;
+    let result = daemon::call::<LocalSocket>(&endpoint, method, params).await?;
+    if !result.is_null() {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    }
+    Ok(())
+}
+
+/// `validate` and `lint`: print every issue of a profile
+///
+/// `lint` also checks against the game's keybinds and fails on warnings.
+fn validate_profile(ctx: &Context, lint: bool) -> Result<()> {
+    let profiles = ctx.profiles();
+    let name = ctx.profile_name(&profiles);
+    let resolved = profiles.resolve(&name)?;
+    let (config, _) = ctx.overrides.apply(&resolved.to_config()?)?;
+
+    let mut report = config.validate_report();
+    if lint {
+        profiles.check_game_keybinds(&config, &mut report);
+    }
+    resolved.annotate(&mut report);
+
+    for issue in &report.issues {
+        println!("{:?}: {}", issue.severity, issue);
+    }
+    let errors = report.errors().count();
+    let warnings = report.warnings().count();
+    println!(
+        "Profile '{}': {} error(s), {} warning(s)",
+        name, errors, warnings
+    );
+
+    if errors > 0 {
+        return Err(Findings::Errors(errors).into());
+    }
+    if lint && warnings > 0 {
+        return Err(Findings::Warnings(warnings).into());
+    }
+    Ok(())
+}
+
+/// `init`: write a config from a template with a schema header for editors
+///
+/// By default the schema is written next to the config and referenced
+/// relatively; `--schema <ref>` points at another file or URL instead.
+fn init_config(
+    ctx: &Context,
+    template: &str,
+    force: bool,
+    schema_ref: Option<String>,
+    no_schema: bool,
+) -> Result<()> {
+    let path = &ctx.location.path;
+    if path.exists() && !force {
+        return Err(anyhow::anyhow!(
+            "{} already exists (use --force to overwrite it)",
+            path.display()
+        ));
+    }
+
+    let format = ConfigFormat::from_path(path)?;
+    let template = templates::find(template)?;
+    let mut content = String::new();
+    if !no_schema {
+        let schema_ref = match schema_ref {
+            Some(schema_ref) => schema_ref,
+            None => {
+                let schema_path = path.with_file_name(schema::SCHEMA_FILE_NAME);
+                if !ctx.skip(format_args!("write {}", schema_path.display())) {
+                    fs::write(&schema_path, schema::config_schema_string())?;
+                    info!("Wrote config schema to {}", schema_path.display());
+                }
+                format!("./{}", schema::SCHEMA_FILE_NAME)
+            }
+        };
+        // Only YAML has a comment to reference the schema from
+        if format == ConfigFormat::Yaml {
+            content.push_str(&schema::header(&schema_ref));
+        }
+    }
+    content.push_str(&template.render(format)?);
+
+    if ctx.skip(format_args!("write {}", path.display())) {
+        return Ok(());
+    }
+    storage::write_with_backup(path, &content)?;
+    info!(
+        "Wrote config from template '{}' to {}",
+        template.name,
+        path.display()
+    );
+    Ok(())
+}
+
+/// `list-keys`: everything a macro can press or be toggled with
+fn list_keys() {
+    println!("Keys (key):            {}", keys::SUPPORTED_KEYS.join(" "));
+    println!(
+        "Hotkeys:               {}",
+        keys::SUPPORTED_HOTKEYS.join(" ")
+    );
+    println!("Mouse buttons:         left right middle");
+    println!("Game actions:          flask_1 .. flask_5, skill_slot_1 .. skill_slot_13,");
+    println!("                       or an action name from the game config (see `keybinds`)");
+}
+
+/// `simulate`: when each macro would fire over the next `duration_ms`
+fn simulate(ctx: &Context, duration_ms: u64) -> Result<()> {
+    let profiles = ctx.profiles();
+    let name = ctx.profile_name(&profiles);
+    let (config, _) = ctx.overrides.apply(&ctx.load_profile(&profiles, &name)?)?;
+    let (config, _) = profiles.resolve_game_actions(&config)?;
+    config.validate_report().into_result()?;
+
+    let mut events = Vec::new();
+    for macro_config in &config.macros {
+        let mut action = match macro_config.action_type {
+            ActionType::Keyboard => format!("press {}", macro_config.key),
+            ActionType::Mouse => format!(
+                "click {}",
+                MacroEngineState::button_name(&macro_config.mouse_button)
+            ),
+        };
+        if let Some(game_action) = &macro_config.game_action {
+            action.push_str(&format!(" ({})", game_action));
+        }
+        let mut at = 0;
+        loop {
+            at += MacroEngineState::calculate_interval(
+                macro_config.interval_ms,
+                macro_config.random_variance_ms,
+            )
+            .as_millis() as u64;
+            if at > duration_ms {
+                break;
+            }
+            events.push((at, macro_config.label(), action.clone()));
+        }
+    }
+    events.sort_by_key(|(at, _, _)| *at);
+
+    println!(
+        "# Profile '{}', first {:.1}s with every macro enabled",
+        name,
+        duration_ms as f64 / 1000.0
+    );
+    for (at, label, action) in &events {
+        println!("{:>9.3}s  {:<24} {}", *at as f64 / 1000.0, label, action);
+    }
+    Ok(())
+}
+
+/// `profile`: list, switch, export, import or resolve profiles
+fn profile_command(ctx: &Context, command: ProfileCommand) -> Result<()> {
+    let profiles = ctx.profiles();
+    match command {
+        ProfileCommand::List => {
+            let active = profiles.active();
+            for name in profiles.list()? {
+                let marker = if name == active { "*" } else { " " };
+                println!("{} {}", marker, name);
+            }
+            Ok(())
+        }
+        ProfileCommand::Switch { name } => {
+            if !profiles.exists(&name) {
+                return Err(anyhow::anyhow!("Profile '{}' does not exist", name));
+            }
+            if !ctx.skip(format_args!("switch to profile '{}'", name)) {
+                profiles.set_active(&name)?;
+            }
+            Ok(())
+        }
+        ProfileCommand::Export { name } => export_profile(
+            ctx,
+            &profiles,
+            name.unwrap_or_else(|| ctx.profile_name(&profiles)),
+        ),
+        ProfileCommand::Import {
+            code,
+            as_profile,
+            replace,
+        } => import_profile(ctx, &profiles, &code, as_profile, replace),
+        ProfileCommand::Resolve { name } => resolve_profile(
+            &profiles,
+            name.unwrap_or_else(|| ctx.profile_name(&profiles)),
+        ),
+    }
+}
 
-    // Create and run macro engine
-    let mut engine = MacroEngine::new(config);
-    engine.run().await?;
+/// `templates`: list, show or instantiate the built-in templates
+fn templates_command(ctx: &Context, name: Option<&str>, profile: Option<String>) -> Result<()> {
+    let Some(name) = name else {
+        for template in templates::all() {
+            println!("{:<16} {}", template.name, template.description);
+        }
+        return Ok(());
+    };
+
+    let template = templates::find(name)?;
+    let Some(profile) = profile else {
+        print!("{}", template.content);
+        return Ok(());
+    };
+    if ctx.skip(format_args!("create profile '{}'", profile)) {
+        return Ok(());
+    }
+    ctx.profiles().create_from_template(&profile, template)?;
+    println!("Created profile '{}' from template '{}'", profile, name);
+    Ok(())
+}
+
+/// `profile export`: print the share code of a profile
+fn export_profile(ctx: &Context, profiles: &ProfileStore, name: String) -> Result<()> {
+    let config = ctx.load_profile(profiles, &name)?;
+    println!("{}", share::encode(&config)?);
+    info!(
+        "Exported profile '{}' ({} macros)",
+        name,
+        config.macros.len()
+    );
+    Ok(())
+}
+
+/// `profile import`: show what a share code contains, importing it with `--as`
+fn import_profile(
+    ctx: &Context,
+    profiles: &ProfileStore,
+    code: &str,
+    name: Option<String>,
+    replace: bool,
+) -> Result<()> {
+    let preview = share::decode(code)?;
+
+    println!("Profile code with {} macros:", preview.config.macros.len());
+    for macro_config in &preview.config.macros {
+        println!(
+            "  {}: {}",
+            macro_config.label(),
+            MacroEngineState::describe(macro_config)
+        );
+    }
+    if let Some(migration) = &preview.migration {
+        println!(
+            "Upgraded from config version {} to {}:",
+            migration.from_version, migration.to_version
+        );
+        for change in &migration.changes {
+            println!("  - {}", change);
+        }
+    }
+    for issue in &preview.validation.issues {
+        println!("  {}", issue);
+    }
+
+    let Some(name) = name else {
+        println!("Run again with --as <profile> to import it");
+        return Ok(());
+    };
+    preview.validation.into_result()?;
+    if ctx.skip(format_args!("import it as profile '{}'", name)) {
+        return Ok(());
+    }
+    profiles.import(&name, &preview.config, replace)?;
+    println!("Imported as profile '{}'", name);
+    Ok(())
+}
+
+/// `profile resolve`: show the merged result of profile inheritance
+///
+/// The files that were merged and any validation issues are printed as YAML
+/// comments above the config.
+fn resolve_profile(profiles: &ProfileStore, name: String) -> Result<()> {
+    let resolved = profiles.resolve(&name)?;
+    let config = resolved.to_config()?;
+    let mut report = config.validate_report();
+    profiles.check_game_keybinds(&config, &mut report);
+    resolved.annotate(&mut report);
+
+    println!("# Profile '{}', merged from:", name);
+    for layer in &resolved.layers {
+        println!("#   {}", layer);
+    }
+    for issue in &report.issues {
+        println!("# {:?}: {}", issue.severity, issue);
+    }
+    print!("{}", serde_yaml::to_string(&config)?);
+    report.into_result()
+}
+
+/// `diff`: compare two profiles, files or share codes
+fn diff_configs(ctx: &Context, sources: &[String]) -> Result<()> {
+    let profiles = ctx.profiles();
+    let current = ctx.profile_name(&profiles);
+    let (before, after) = match sources {
+        [after] => (current.as_str(), after.as_str()),
+        [before, after] => (before.as_str(), after.as_str()),
+        _ => unreachable!("the argument parser allows one or two sources"),
+    };
+
+    let before = load_source(&profiles, before)?;
+    let after = load_source(&profiles, after)?;
+    print!("{}", diff::diff(&before, &after));
+    Ok(())
+}
+
+/// `convert`: rewrite a config file in another format
+fn convert_config(ctx: &Context, input: &Path, output: &Path, force: bool) -> Result<()> {
+    if output.exists() && !force {
+        return Err(anyhow::anyhow!(
+            "{} already exists (use --force to overwrite it)",
+            output.display()
+        ));
+    }
+
+    let from = ConfigFormat::from_path(input)?;
+    let to = ConfigFormat::from_path(output)?;
+    let converted = Config::convert(&fs::read_to_string(input)?, from, to)?;
+    if ctx.skip(format_args!("write {}", output.display())) {
+        return Ok(());
+    }
+    storage::write_with_backup(output, &converted)?;
+
+    info!(
+        "Converted {} ({}) to {} ({})",
+        input.display(),
+        from,
+        output.display(),
+        to
+    );
+    if from != ConfigFormat::Json {
+        info!("Comments are not carried over");
+    }
+    Ok(())
+}
+
+/// `keybinds`: list the game's keybinds and check the profile against them
+fn show_keybinds(ctx: &Context, path: Option<String>) -> Result<()> {
+    let profiles = ctx.profiles();
+    let configured = match path {
+        Some(path) => Some(path),
+        None => profiles.game_config()?,
+    };
+    let path = game_keybinds::locate(configured.as_deref()).ok_or_else(|| {
+        anyhow::anyhow!("No documents folder to look for the game config in; pass its path")
+    })?;
+    let keybinds = GameKeybinds::load(&path)?;
+
+    println!("# {}", path.display());
+    for bind in &keybinds.binds {
+        let modifier = if bind.modified {
+            " (with modifier)"
+        } else {
+            ""
+        };
+        println!("{:<40} {}{}", bind.action, bind.key, modifier);
+    }
+
+    let config = ctx.load_profile(&profiles, &ctx.profile_name(&profiles))?;
+    let mut report = ValidationReport::new();
+    keybinds.check(&config, &mut report);
+    for issue in &report.issues {
+        println!("# {:?}: {}", issue.severity, issue);
+    }
+    Ok(())
+}
 
+/// `schema`: print the config JSON Schema, or write it with `--output`
+fn write_schema(ctx: &Context, output: Option<PathBuf>) -> Result<()> {
+    let schema = schema::config_schema_string();
+    match output {
+        Some(path) => {
+            if !ctx.skip(format_args!("write {}", path.display())) {
+                fs::write(&path, schema)?;
+                info!("Wrote config schema to {}", path.display());
+            }
+        }
+        None => println!("{}", schema),
+    }
     Ok(())
 }
 
-/// Check if CLI mode is requested
-pub fn is_cli_mode() -> bool {
-    let args: Vec<String> = env::args().collect();
-    args.len() > 1 && args[1].to_lowercase() == "cli"
+/// Config named by a share code, a file path or a profile name
+///
+/// Values with a path separator or a file extension are files, read like a
+/// main config file so their own `extends` resolve.
+fn load_source(profiles: &ProfileStore, source: &str) -> Result<Config> {
+    if source.starts_with(share::CODE_PREFIX) {
+        return Ok(share::decode(source)?.config);
+    }
+    let path = Path::new(source);
+    if source.contains(['/', '\\']) || path.extension().is_some() {
+        return ProfileStore::open(path).read(DEFAULT_PROFILE);
+    }
+    if !profiles.exists(source) {
+        return Err(anyhow::anyhow!("Profile '{}' does not exist", source));
+    }
+    profiles.read(source)
 }
@@ -1,72 +1,282 @@
-use crate::config::Config;
+use crate::config::{Config, KeyMacro, LoadReport};
+use crate::diff::{self, ConfigDiff};
+use crate::game_keybinds::{self, GameKeybinds};
 use crate::macro_engine::{MacroEngineState, MacroStatus};
+use crate::paths::ConfigLocation;
+use crate::profiles::{ProfileList, ProfileStore};
+use crate::schema;
+use crate::session::SessionStatus;
+use crate::share::{self, ImportPreview};
+use crate::storage::FileVersion;
+use crate::templates::{self, Template, TemplatePreview};
+use crate::validation::ValidationReport;
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::State;
 use tokio::sync::RwLock;
+use tracing::warn;
 
-const CONFIG_PATH: &str = "config.yaml";
-
-/// Cached configuration to avoid repeated file reads
+/// Cached configuration of the active profile to avoid repeated file reads
+///
+/// Also remembers which version of the profile's file the config came from.
+/// The GUI sends that version back when saving, so changes made meanwhile in
+/// an editor or by the CLI aren't silently overwritten.
+#[derive(Clone)]
 pub struct ConfigCache {
-    config: Arc<RwLock<Option<Config>>>,
+    config: Arc<RwLock<Option<Cached>>>,
+    load_report: Arc<RwLock<Option<LoadReport>>>,
+    profiles: ProfileStore,
+}
+
+/// Config of a profile and the version of its file it matches
+struct Cached {
+    profile: String,
+    config: Config,
+    /// `None` when the file didn't exist yet
+    version: Option<FileVersion>,
+}
+
+/// A config as the GUI received it, with the version of the file it came from
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadedConfig {
+    pub config: Config,
+    /// Content hash of the profile's file, `None` when it didn't exist yet
+    pub version: Option<u32>,
 }
 
-impl Default for ConfigCache {
-    fn default() -> Self {
-        Self::new()
+/// Why the GUI's config couldn't be saved
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SaveError {
+    /// The profile's file changed since it was loaded; nothing was written
+    Conflict {
+        path: PathBuf,
+        /// When the file was last modified, as RFC 3339
+        modified: Option<String>,
+        /// The config that was about to be saved
+        ours: Box<Config>,
+        /// What the file holds now, or `None` when it was deleted or doesn't parse
+        theirs: Option<Box<Config>>,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Conflict { path, .. } => write!(
+                f,
+                "{} was changed by another program since it was loaded",
+                path.display()
+            ),
+            Self::Failed { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<anyhow::Error> for SaveError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Failed {
+            message: e.to_string(),
+        }
     }
 }
 
 impl ConfigCache {
-    pub fn new() -> Self {
+    pub fn new(profiles: ProfileStore) -> Self {
         Self {
             config: Arc::new(RwLock::new(None)),
+            load_report: Arc::new(RwLock::new(None)),
+            profiles,
         }
     }
 
     pub async fn get_or_load(&self) -> Result<Config, String> {
-        // Try to get from cache first
+        self.get_or_load_versioned()
+            .await
+            .map(|loaded| loaded.config)
+    }
+
+    /// Config of the active profile and the version of its file it matches
+    pub async fn get_or_load_versioned(&self) -> Result<LoadedConfig, String> {
+        let active = self.profiles.active();
+
+        // Try to get from cache first (unless the active profile changed)
         {
             let cache = self.config.read().await;
-            if let Some(config) = cache.as_ref() {
-                return Ok(config.clone());
+            if let Some(cached) = cache.as_ref() {
+                if cached.profile == active {
+                    return Ok(cached.loaded());
+                }
             }
         }
 
-        // Load from file and cache
-        let config = Config::load_or_default(CONFIG_PATH).map_err(|e| e.to_string())?;
-        {
-            let mut cache = self.config.write().await;
-            *cache = Some(config.clone());
+        // Load from file and cache, remembering any upgrade or restore for the UI
+        let (config, report) = self
+            .profiles
+            .load_with_report(&active)
+            .map_err(|e| e.to_string())?;
+        if report.migration.is_some() || report.restored_backup.is_some() {
+            *self.load_report.write().await = Some(report);
+        }
+        let mut cache = self.config.write().await;
+        Ok(self.store(&mut cache, active, config))
+    }
+
+    /// Drop the cached config and read the active profile from disk again
+    pub async fn reload(&self) -> Result<LoadedConfig, String> {
+        *self.config.write().await = None;
+        self.get_or_load_versioned().await
+    }
+
+    /// Save a config as the active profile, giving macros without an id one
+    ///
+    /// `version` is the one the GUI loaded the config with. Fails with
+    /// [`SaveError::Conflict`] when the profile's file no longer matches it,
+    /// unless `force` is set. Returns the version of the saved file.
+    pub async fn save(
+        &self,
+        config: &Config,
+        version: Option<u32>,
+        force: bool,
+    ) -> Result<Option<u32>, SaveError> {
+        let mut config = config.clone();
+        config.assign_ids();
+        let active = self.profiles.active();
+        let path = self.profiles.path_of(&active);
+
+        // Hold the lock until the new version is recorded, so saves don't interleave
+        let mut cache = self.config.write().await;
+        if !force {
+            let current = FileVersion::of(&path);
+            if current.as_ref().map(|current| current.hash) != version {
+                warn!(
+                    "Not saving profile '{}': {} changed since it was loaded",
+                    active,
+                    path.display()
+                );
+                return Err(SaveError::Conflict {
+                    path,
+                    modified: current
+                        .and_then(|version| version.modified)
+                        .map(|time| time.to_rfc3339()),
+                    ours: Box::new(config),
+                    theirs: self.profiles.read(&active).ok().map(Box::new),
+                });
+            }
         }
-        Ok(config)
+
+        self.profiles.save(&active, &config)?;
+        Ok(self.store(&mut cache, active, config).version)
     }
 
-    pub async fn invalidate(&self) {
+    /// Replace the cached config of the active profile with what is on disk
+    ///
+    /// Returns `None` when the cache already held the same config, e.g. when
+    /// the file watcher sees our own save.
+    pub async fn update(&self, config: Config) -> Option<LoadedConfig> {
+        let active = self.profiles.active();
         let mut cache = self.config.write().await;
-        *cache = None;
+        let changed = !cache
+            .as_ref()
+            .is_some_and(|c| c.profile == active && c.config == config);
+        let loaded = self.store(&mut cache, active, config);
+        changed.then_some(loaded)
+    }
+
+    /// Cache `config` together with the current version of the profile's file
+    fn store(&self, cache: &mut Option<Cached>, profile: String, config: Config) -> LoadedConfig {
+        let version = FileVersion::of(&self.profiles.path_of(&profile));
+        let cached = cache.insert(Cached {
+            profile,
+            config,
+            version,
+        });
+        cached.loaded()
+    }
+
+    /// Last upgrade or backup restore that happened while loading, if any
+    pub async fn load_report(&self) -> Option<LoadReport> {
+        self.load_report.read().await.clone()
+    }
+}
+
+impl Cached {
+    fn loaded(&self) -> LoadedConfig {
+        LoadedConfig {
+            config: self.config.clone(),
+            version: self.version.as_ref().map(|version| version.hash),
+        }
     }
 }
 
 #[tauri::command]
-pub async fn load_config(cache: State<'_, ConfigCache>) -> Result<Config, String> {
-    cache.get_or_load().await
+pub async fn load_config(cache: State<'_, ConfigCache>) -> Result<LoadedConfig, String> {
+    cache.get_or_load_versioned().await
 }
 
+/// Read the active profile from disk again, e.g. to take changes made elsewhere
 #[tauri::command]
-pub async fn save_config(config: Config, cache: State<'_, ConfigCache>) -> Result<(), String> {
-    config.save(CONFIG_PATH).map_err(|e| e.to_string())?;
-    // Invalidate cache after save
-    cache.invalidate().await;
-    Ok(())
+pub async fn reload_config(cache: State<'_, ConfigCache>) -> Result<LoadedConfig, String> {
+    cache.reload().await
 }
 
 #[tauri::command]
-pub async fn validate_config(config: Config) -> Result<Option<String>, String> {
-    match config.validate() {
-        Ok(_) => Ok(None),
-        Err(e) => Ok(Some(e.to_string())),
-    }
+pub async fn get_config_location(
+    location: State<'_, ConfigLocation>,
+) -> Result<ConfigLocation, String> {
+    Ok(location.inner().clone())
+}
+
+#[tauri::command]
+pub async fn get_load_report(cache: State<'_, ConfigCache>) -> Result<Option<LoadReport>, String> {
+    Ok(cache.load_report().await)
+}
+
+/// Save the active profile, refusing to overwrite changes made on disk
+/// since `version` was loaded unless `force` is set
+#[tauri::command]
+pub async fn save_config(
+    config: Config,
+    version: Option<u32>,
+    force: Option<bool>,
+    cache: State<'_, ConfigCache>,
+) -> Result<Option<u32>, SaveError> {
+    cache.save(&config, version, force.unwrap_or(false)).await
+}
+
+/// JSON Schema of the config file format
+#[tauri::command]
+pub async fn get_config_schema() -> Result<serde_json::Value, String> {
+    Ok(schema::config_schema())
+}
+
+/// Every issue in a config, including clashes with the game's keybinds
+#[tauri::command]
+pub async fn validate_config(
+    mut config: Config,
+    state: State<'_, MacroEngineState>,
+) -> Result<ValidationReport, String> {
+    config.assign_ids();
+    let mut report = config.validate_report();
+    state.profiles().check_game_keybinds(&config, &mut report);
+    Ok(report)
+}
+
+/// The game's keybinds, or `None` when its config file can't be found
+#[tauri::command]
+pub async fn get_game_keybinds(
+    state: State<'_, MacroEngineState>,
+) -> Result<Option<GameKeybinds>, String> {
+    let configured = state.profiles().game_config().map_err(|e| e.to_string())?;
+    Ok(
+        game_keybinds::locate(configured.as_deref())
+            .and_then(|path| GameKeybinds::load(&path).ok()),
+    )
 }
 
 #[tauri::command]
@@ -92,6 +302,273 @@ pub async fn get_macro_status(
 }
 
 #[tauri::command]
-pub async fn toggle_macro(index: usize, state: State<'_, MacroEngineState>) -> Result<(), String> {
-    state.toggle_macro(index).await.map_err(|e| e.to_string())
+pub async fn toggle_macro(id: String, state: State<'_, MacroEngineState>) -> Result<(), String> {
+    state.toggle_macro(&id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_macro(
+    id: String,
+    macro_config: KeyMacro,
+    state: State<'_, MacroEngineState>,
+) -> Result<Config, String> {
+    state
+        .update_macro(&id, macro_config)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_macro(
+    macro_config: KeyMacro,
+    state: State<'_, MacroEngineState>,
+) -> Result<Config, String> {
+    state
+        .add_macro(macro_config)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn remove_macro(
+    id: String,
+    state: State<'_, MacroEngineState>,
+) -> Result<Config, String> {
+    state.remove_macro(&id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn reset_macro_errors(
+    id: Option<String>,
+    state: State<'_, MacroEngineState>,
+) -> Result<(), String> {
+    state
+        .reset_errors(id.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_session_status(
+    state: State<'_, MacroEngineState>,
+) -> Result<SessionStatus, String> {
+    Ok(state.get_session_status().await)
+}
+
+#[tauri::command]
+pub async fn list_profiles(state: State<'_, MacroEngineState>) -> Result<ProfileList, String> {
+    state.profiles().summary().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn switch_profile(
+    name: String,
+    state: State<'_, MacroEngineState>,
+    cache: State<'_, ConfigCache>,
+) -> Result<LoadedConfig, String> {
+    state
+        .switch_profile(&name)
+        .await
+        .map_err(|e| e.to_string())?;
+    cache.get_or_load_versioned().await
+}
+
+#[tauri::command]
+pub async fn create_profile(
+    name: String,
+    state: State<'_, MacroEngineState>,
+    cache: State<'_, ConfigCache>,
+) -> Result<(), String> {
+    // New profiles start as a copy of the active one
+    let config = cache.get_or_load().await?;
+    state
+        .profiles()
+        .create(&name, &config)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_profile(
+    name: String,
+    state: State<'_, MacroEngineState>,
+) -> Result<(), String> {
+    state.profiles().delete(&name).map_err(|e| e.to_string())
+}
+
+/// Share code of a profile, the active one by default
+#[tauri::command]
+pub async fn export_profile(
+    name: Option<String>,
+    state: State<'_, MacroEngineState>,
+    cache: State<'_, ConfigCache>,
+) -> Result<String, String> {
+    let config = match name {
+        Some(name) if name != state.profiles().active() => {
+            state.profiles().load(&name).map_err(|e| e.to_string())?
+        }
+        _ => cache.get_or_load().await?,
+    };
+    share::encode(&config).map_err(|e| e.to_string())
+}
+
+/// Decode a share code so the user can review it before importing
+#[tauri::command]
+pub async fn preview_profile_code(code: String) -> Result<ImportPreview, String> {
+    share::decode(&code).map_err(|e| e.to_string())
+}
+
+/// Import a share code as the named profile
+#[tauri::command]
+pub async fn import_profile_code(
+    code: String,
+    name: String,
+    replace: bool,
+    state: State<'_, MacroEngineState>,
+    cache: State<'_, ConfigCache>,
+) -> Result<ImportPreview, String> {
+    let preview = share::decode(&code).map_err(|e| e.to_string())?;
+    preview
+        .validation
+        .clone()
+        .into_result()
+        .map_err(|e| e.to_string())?;
+    state
+        .profiles()
+        .import(&name, &preview.config, replace)
+        .map_err(|e| e.to_string())?;
+
+    // The file watcher applies the new file to a running engine
+    if name == state.profiles().active() {
+        let config = state.profiles().load(&name).map_err(|e| e.to_string())?;
+        cache.update(config).await;
+    }
+    Ok(preview)
+}
+
+/// Macros and settings that differ between two configs, e.g. before saving
+#[tauri::command]
+pub async fn diff_configs(mut before: Config, mut after: Config) -> Result<ConfigDiff, String> {
+    before.assign_ids();
+    after.assign_ids();
+    Ok(diff::diff(&before, &after))
+}
+
+/// What importing a share code would change in a profile, the active one by default
+#[tauri::command]
+pub async fn diff_profile_code(
+    code: String,
+    name: Option<String>,
+    state: State<'_, MacroEngineState>,
+    cache: State<'_, ConfigCache>,
+) -> Result<ConfigDiff, String> {
+    let preview = share::decode(&code).map_err(|e| e.to_string())?;
+    let current = match name {
+        Some(name) if name != state.profiles().active() => {
+            state.profiles().read(&name).map_err(|e| e.to_string())?
+        }
+        _ => cache.get_or_load().await?,
+    };
+    Ok(diff::diff(&current, &preview.config))
+}
+
+/// Built-in templates to start a profile from
+#[tauri::command]
+pub async fn list_templates() -> Result<Vec<Template>, String> {
+    Ok(templates::all().to_vec())
+}
+
+/// A template and the config it produces, to review before using it
+#[tauri::command]
+pub async fn preview_template(name: String) -> Result<TemplatePreview, String> {
+    templates::find(&name)
+        .and_then(|template| template.preview())
+        .map_err(|e| e.to_string())
+}
+
+/// Create a new profile from a template
+#[tauri::command]
+pub async fn create_profile_from_template(
+    name: String,
+    template: String,
+    state: State<'_, MacroEngineState>,
+) -> Result<(), String> {
+    let template = templates::find(&template).map_err(|e| e.to_string())?;
+    state
+        .profiles()
+        .create_from_template(&name, template)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profiles::DEFAULT_PROFILE;
+    use std::fs;
+
+    fn cache_with(content: &str) -> (tempfile::TempDir, PathBuf, ConfigCache) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, content).unwrap();
+        let cache = ConfigCache::new(ProfileStore::open(&path));
+        (dir, path, cache)
+    }
+
+    const ONE_MACRO: &str =
+        "version: 2\nmacros:\n  - key: q\n    interval_ms: 1000\n    toggle_hotkey: F9\n";
+
+    #[tokio::test]
+    async fn saving_over_an_edit_on_disk_is_a_conflict() {
+        let (_dir, path, cache) = cache_with(ONE_MACRO);
+        let loaded = cache.get_or_load_versioned().await.unwrap();
+
+        let edited = ONE_MACRO.replace("1000", "500");
+        fs::write(&path, &edited).unwrap();
+
+        let mut ours = loaded.config.clone();
+        ours.macros[0].interval_ms = 2000;
+        let result = cache.save(&ours, loaded.version, false).await;
+        assert!(matches!(
+            result,
+            Err(SaveError::Conflict { theirs: Some(ref theirs), .. })
+                if theirs.macros[0].interval_ms == 500
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), edited);
+    }
+
+    #[tokio::test]
+    async fn a_hot_reload_does_not_hide_the_conflict() {
+        let (_dir, path, cache) = cache_with(ONE_MACRO);
+        let loaded = cache.get_or_load_versioned().await.unwrap();
+
+        // The file watcher picks up the edit while the GUI still shows its own changes
+        fs::write(&path, ONE_MACRO.replace("1000", "500")).unwrap();
+        let reloaded = cache
+            .update(cache.profiles.read(DEFAULT_PROFILE).unwrap())
+            .await;
+        assert!(reloaded.is_some_and(|reloaded| reloaded.version != loaded.version));
+
+        let result = cache.save(&loaded.config, loaded.version, false).await;
+        assert!(matches!(result, Err(SaveError::Conflict { .. })));
+    }
+
+    #[tokio::test]
+    async fn saves_with_the_current_version_or_forced_go_through() {
+        let (_dir, path, cache) = cache_with(ONE_MACRO);
+        let loaded = cache.get_or_load_versioned().await.unwrap();
+
+        let mut config = loaded.config.clone();
+        config.macros[0].interval_ms = 2000;
+        let version = cache.save(&config, loaded.version, false).await.unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("2000"));
+
+        // Saving again with the version the first save returned
+        config.macros[0].interval_ms = 3000;
+        cache.save(&config, version, false).await.unwrap();
+
+        fs::write(&path, ONE_MACRO).unwrap();
+        config.macros[0].interval_ms = 4000;
+        assert!(cache.save(&config, version, false).await.is_err());
+        cache.save(&config, version, true).await.unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("4000"));
+    }
 }
@@ -1,10 +1,24 @@
 use anyhow::Result;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use crate::duration::{Interval, Millis};
+use crate::format::ConfigFormat;
+use crate::inheritance;
+use crate::keys;
+use crate::migration::{self, MigrationReport};
+use crate::schema;
+use crate::storage;
+use crate::templates;
+use crate::validation::ValidationReport;
+use crate::yaml_edit;
 
 /// Type of action for a macro
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ActionType {
     #[default]
@@ -13,7 +27,7 @@ pub enum ActionType {
 }
 
 /// Mouse button type
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum MouseButton {
     Left,
@@ -22,8 +36,24 @@ pub enum MouseButton {
 }
 
 /// Configuration for a single macro (keyboard or mouse)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Timings may be written as durations (`"1.5s"`) or as an `interval` range
+/// (`"1.2s..1.8s"`) but are always saved as `interval_ms`/`random_variance_ms`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(try_from = "RawKeyMacro")]
 pub struct KeyMacro {
+    /// Stable identifier the running engine addresses this macro by
+    ///
+    /// Derived from the name, or from the settings of unnamed macros, when the
+    /// file doesn't set one and written on the next save; see [`Config::assign_ids`].
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+
+    /// Optional name, shown in logs and used by profiles that extend this one
+    /// to override or remove the macro
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
     /// Type of action (keyboard or mouse)
     #[serde(default)]
     pub action_type: ActionType,
@@ -32,6 +62,11 @@ pub struct KeyMacro {
     #[serde(default)]
     pub key: String,
 
+    /// Game action to press the key of instead, e.g. `flask_1` or `skill_slot_3`,
+    /// looked up in the game's keybinds when the engine starts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_action: Option<String>,
+
     /// Mouse button to click - used when action_type is Mouse
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mouse_button: Option<MouseButton>,
@@ -51,125 +86,801 @@ pub struct KeyMacro {
     pub enabled_by_default: bool,
 }
 
+/// `KeyMacro` as written in a config file, before timings are normalized
+///
+/// This is also what the exported JSON Schema describes.
+#[derive(Deserialize, JsonSchema)]
+#[schemars(
+    rename = "KeyMacro",
+    deny_unknown_fields,
+    transform = crate::schema::interval_alternatives
+)]
+struct RawKeyMacro {
+    /// Stable identifier; derived from the name or settings when omitted
+    id: Option<String>,
+    /// Name shown in logs, and that extending profiles use to override or remove this macro
+    name: Option<String>,
+    /// Type of action (keyboard or mouse)
+    #[serde(default)]
+    action_type: ActionType,
+    /// Key to press - used when action_type is keyboard
+    #[serde(default)]
+    #[schemars(schema_with = "crate::schema::key_name")]
+    key: String,
+    /// Game action to press the bound key of instead of `key`, e.g. `flask_1`
+    /// or `skill_slot_3`, looked up in the game's keybinds
+    game_action: Option<String>,
+    /// Mouse button to click - used when action_type is mouse
+    mouse_button: Option<MouseButton>,
+    /// Base interval between actions
+    interval_ms: Option<Millis>,
+    /// Random variance (±)
+    random_variance_ms: Option<Millis>,
+    /// Alternative to interval_ms; a range also sets the variance
+    interval: Option<Interval>,
+    /// Hotkey to toggle this macro on/off
+    #[schemars(schema_with = "crate::schema::hotkey_name")]
+    toggle_hotkey: String,
+    /// Whether this macro starts enabled
+    #[serde(default)]
+    enabled_by_default: bool,
+}
+
+impl KeyMacro {
+    /// How log messages refer to this macro: its name, or its id without one
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+}
+
+impl TryFrom<RawKeyMacro> for KeyMacro {
+    type Error = String;
+
+    fn try_from(raw: RawKeyMacro) -> Result<Self, Self::Error> {
+        let variance = raw.random_variance_ms.map(|Millis(ms)| ms);
+        let (interval_ms, random_variance_ms) = match (raw.interval, raw.interval_ms) {
+            (Some(_), Some(_)) => {
+                return Err("set either `interval` or `interval_ms`, not both".to_string())
+            }
+            (Some(Interval::Range { .. }), _) if variance.is_some() => {
+                return Err(
+                    "`interval` is a range, which already sets the variance; remove `random_variance_ms`"
+                        .to_string(),
+                )
+            }
+            (Some(Interval::Range { base, variance }), None) => (base, variance),
+            (Some(Interval::Fixed(ms)), None) | (None, Some(Millis(ms))) => {
+                (ms, variance.unwrap_or(0))
+            }
+            (None, None) => return Err("missing field `interval_ms` (or `interval`)".to_string()),
+        };
+
+        Ok(Self {
+            id: raw.id.unwrap_or_default(),
+            name: raw.name,
+            action_type: raw.action_type,
+            key: raw.key,
+            game_action: raw.game_action,
+            mouse_button: raw.mouse_button,
+            interval_ms,
+            random_variance_ms,
+            toggle_hotkey: raw.toggle_hotkey,
+            enabled_by_default: raw.enabled_by_default,
+        })
+    }
+}
+
+/// Self-imposed limits on how long the engine may run
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Maximum continuous engine runtime in minutes, after which the engine stops itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_runtime_minutes: Option<u64>,
+
+    /// Log a break reminder every N minutes while the engine runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_reminder_minutes: Option<u64>,
+}
+
+/// What happened while loading a config file, for reporting to the user
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct LoadReport {
+    /// Schema upgrade applied to the file
+    pub migration: Option<MigrationReport>,
+    /// Backup restored because the file itself could not be parsed
+    pub restored_backup: Option<String>,
+}
+
 /// Configuration for macro behavior
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[schemars(deny_unknown_fields, transform = crate::schema::extends_property)]
 pub struct Config {
+    /// Schema version of this file, upgraded automatically on load
+    #[serde(default = "migration::current_version")]
+    pub version: u32,
+
     /// List of key macros to run
     pub macros: Vec<KeyMacro>,
+
+    /// Session time limits and break reminders
+    #[serde(default)]
+    pub session: SessionConfig,
+
+    /// Name of the profile to run (only read from the main config file)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+
+    /// Hotkey that cycles through profiles while the engine runs (only read from the main config file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "crate::schema::optional_hotkey_name")]
+    pub profile_cycle_hotkey: Option<String>,
+
+    /// Path of the game's `poe2_production_Config.ini`, to check hotkeys against
+    /// its keybinds; found in Documents by default (only read from the main config file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_config: Option<String>,
 }
 
+/// The starter template, see [`crate::templates`]
 impl Default for Config {
     fn default() -> Self {
-        Self {
-            macros: vec![
-                KeyMacro {
-                    action_type: ActionType::Keyboard,
-                    key: "1".to_string(),
-                    mouse_button: None,
-                    interval_ms: 1000,
-                    random_variance_ms: 200,
-                    toggle_hotkey: "F9".to_string(),
-                    enabled_by_default: false,
-                },
-                KeyMacro {
-                    action_type: ActionType::Keyboard,
-                    key: "e".to_string(),
-                    mouse_button: None,
-                    interval_ms: 1500,
-                    random_variance_ms: 300,
-                    toggle_hotkey: "F10".to_string(),
-                    enabled_by_default: false,
-                },
-                KeyMacro {
-                    action_type: ActionType::Mouse,
-                    key: String::new(),
-                    mouse_button: Some(MouseButton::Left),
-                    interval_ms: 800,
-                    random_variance_ms: 150,
-                    toggle_hotkey: "F11".to_string(),
-                    enabled_by_default: false,
-                },
-            ],
-        }
+        templates::find(templates::DEFAULT_TEMPLATE)
+            .and_then(|template| template.config())
+            .expect("the built-in starter template is valid")
     }
 }
 
 impl Config {
-    /// Load configuration from YAML file
+    /// Load configuration from a YAML, TOML or JSON file, upgrading older versions in memory
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load_with_report(path).map(|(config, _)| config)
+    }
+
+    /// Load configuration, reporting any upgrade or backup restore it needs
+    ///
+    /// Nothing is written: a file that can't be parsed is replaced in memory
+    /// by the newest backup that parses, and older schema versions are
+    /// upgraded in memory. [`Config::persist_load`] writes both back.
+    pub fn load_with_report<P: AsRef<Path>>(path: P) -> Result<(Self, LoadReport)> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)?;
+        let mut report = LoadReport::default();
+
         let content = fs::read_to_string(path)?;
-        let config: Config = serde_yaml::from_str(&content)?;
-        Ok(config)
+        let (config, migration) = match Self::parse(&content, format) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Config {} could not be parsed: {}", path.display(), e);
+                let (backup, parsed) =
+                    Self::newest_valid_backup(path, format).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "{} is corrupt ({}) and no valid backup was found",
+                            path.display(),
+                            e
+                        )
+                    })?;
+                report.restored_backup = Some(backup.display().to_string());
+                parsed
+            }
+        };
+        report.migration = migration;
+
+        Ok((config, report))
     }
 
-    /// Save configuration to YAML file
-    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let yaml = serde_yaml::to_string(self)?;
-        fs::write(path, yaml)?;
+    /// Write back what [`Config::load_with_report`] changed in memory
+    ///
+    /// A corrupt file is moved aside and the backup it was loaded from put in
+    /// its place; an upgraded config is then saved like any other, so the
+    /// pre-upgrade file ends up in the rotating backups.
+    pub fn persist_load<P: AsRef<Path>>(&self, path: P, report: &mut LoadReport) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(backup) = &report.restored_backup {
+            let backup_content = fs::read_to_string(backup)?;
+            let corrupt = storage::quarantine(path)?;
+            storage::atomic_write(path, &backup_content)?;
+            warn!(
+                "Restored config from backup {} (corrupt file moved to {})",
+                backup,
+                corrupt.display()
+            );
+        }
+
+        if let Some(migration) = &mut report.migration {
+            self.save(path)?;
+            let backup = storage::list_backups(path)?
+                .into_iter()
+                .next()
+                .map(|backup| backup.display().to_string());
+            info!(
+                "Upgraded config from version {} to {} (original saved to {})",
+                migration.from_version,
+                migration.to_version,
+                backup.as_deref().unwrap_or("nowhere")
+            );
+            for change in &migration.changes {
+                info!("  {}", change);
+            }
+            migration.backup_path = backup;
+        }
         Ok(())
     }
 
-    /// Load config or create default if not exists
+    /// Read a config file without migrating, restoring or rewriting it on disk
+    ///
+    /// Used for hot reload, where a half-written file must not trigger recovery.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let format = ConfigFormat::from_path(path.as_ref())?;
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content, format).map(|(config, _)| config)
+    }
+
+    /// Parse and migrate a config document without touching the file system
+    pub fn parse(content: &str, format: ConfigFormat) -> Result<(Self, Option<MigrationReport>)> {
+        let (mut config, migration) = Self::parse_as_written(content, format)?;
+        config.assign_ids();
+        Ok((config, migration))
+    }
+
+    /// [`Config::parse`] without giving macros that have no id one
+    fn parse_as_written(
+        content: &str,
+        format: ConfigFormat,
+    ) -> Result<(Self, Option<MigrationReport>)> {
+        let mut document = format.parse(content)?;
+        let migration = migration::migrate(&mut document)?;
+        let config: Self = serde_yaml::from_value(document.clone())
+            .map_err(|e| Self::locate_macro_error(&document, e))?;
+        Ok((config, migration))
+    }
+
+    /// Give every macro without an id the one derived for it
+    ///
+    /// Named macros get a slug of their name (`Life Flask` becomes
+    /// `life-flask`), unnamed ones `macro-` and a fingerprint of their
+    /// settings, so ids don't shift when other macros are added, removed or
+    /// reordered. Clashing ids get a `-2`, `-3`, ... suffix.
+    pub fn assign_ids(&mut self) {
+        let mut taken: HashSet<String> = self
+            .macros
+            .iter()
+            .filter(|m| !m.id.is_empty())
+            .map(|m| m.id.clone())
+            .collect();
+        for macro_config in &mut self.macros {
+            if !macro_config.id.is_empty() {
+                continue;
+            }
+            let base = match macro_config.name.as_deref().map(slug) {
+                Some(slug) if !slug.is_empty() => slug,
+                _ => format!("macro-{:08x}", fingerprint(macro_config)),
+            };
+            let mut id = base.clone();
+            let mut suffix = 1;
+            while taken.contains(&id) {
+                suffix += 1;
+                id = format!("{}-{}", base, suffix);
+            }
+            taken.insert(id.clone());
+            macro_config.id = id;
+        }
+    }
+
+    /// This config with the ids that [`Config::assign_ids`] would derive left out
+    ///
+    /// Saved files keep every id; this is for comparing configs and for share
+    /// codes, where only ids that can't be derived again matter.
+    pub fn without_derived_ids(&self) -> Self {
+        let mut derived = self.clone();
+        derived.macros.iter_mut().for_each(|m| m.id.clear());
+        derived.assign_ids();
+
+        let mut stripped = self.clone();
+        for (macro_config, derived) in stripped.macros.iter_mut().zip(&derived.macros) {
+            if macro_config.id == derived.id {
+                macro_config.id.clear();
+            }
+        }
+
+        // Mixing kept and derived ids can shift the suffixes; keep them all then
+        let mut check = stripped.clone();
+        check.assign_ids();
+        if check == *self {
+            stripped
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Rewrite a config document in another format, upgrading it on the way
+    ///
+    /// The document is converted rather than the parsed config, so unknown
+    /// fields and durations written as text survive. The result must read back
+    /// as the same config.
+    pub fn convert(content: &str, from: ConfigFormat, to: ConfigFormat) -> Result<String> {
+        let mut document = from.parse(content)?;
+
+        // Profiles that extend another only hold overrides, so there is no
+        // config to compare; the documents themselves must match instead
+        if document.get(inheritance::EXTENDS_KEY).is_some() {
+            let converted = to.render(&document)?;
+            if to.parse(&converted)? != document {
+                return Err(anyhow::anyhow!(
+                    "Converting to {} would change the profile; nothing was written",
+                    to
+                ));
+            }
+            return Ok(converted);
+        }
+
+        let (config, _) = Self::parse(content, from)?;
+        migration::migrate(&mut document)?;
+
+        let converted = to.render(&document)?;
+        let (converted_config, _) = Self::parse(&converted, to)
+            .map_err(|e| anyhow::anyhow!("Converted {} does not parse: {}", to, e))?;
+        if converted_config != config {
+            return Err(anyhow::anyhow!(
+                "Converting to {} would change the config; nothing was written",
+                to
+            ));
+        }
+        Ok(converted)
+    }
+
+    /// Prefix a parse error with the path of the macro that caused it, if any
+    fn locate_macro_error(document: &serde_yaml::Value, error: serde_yaml::Error) -> anyhow::Error {
+        let macros = document.get("macros").and_then(|m| m.as_sequence());
+        for (i, entry) in macros.into_iter().flatten().enumerate() {
+            if let Err(e) = serde_yaml::from_value::<KeyMacro>(entry.clone()) {
+                return anyhow::anyhow!("macros[{}]: {}", i, e);
+            }
+        }
+        error.into()
+    }
+
+    /// Newest backup of `path` that still parses
+    fn newest_valid_backup(
+        path: &Path,
+        format: ConfigFormat,
+    ) -> Option<(PathBuf, (Self, Option<MigrationReport>))> {
+        storage::list_backups(path)
+            .ok()?
+            .into_iter()
+            .find_map(|backup| {
+                let content = fs::read_to_string(&backup).ok()?;
+                let parsed = Self::parse(&content, format).ok()?;
+                Some((backup, parsed))
+            })
+    }
+
+    /// Save configuration in the format given by the file extension
+    ///
+    /// An existing YAML file is edited in place so comments, field order and
+    /// unknown fields survive; TOML and JSON files are rewritten. The file is
+    /// replaced atomically and the previous version is kept as a rotating backup.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let format = ConfigFormat::from_path(path.as_ref())?;
+        let existing = fs::read_to_string(path.as_ref()).ok();
+        let content = match format {
+            ConfigFormat::Yaml => self.render(existing.as_deref())?,
+            _ => format.render(self)?,
+        };
+        storage::write_with_backup(path.as_ref(), &content)
+    }
+
+    /// YAML for this config, patched into `existing` when possible
+    fn render(&self, existing: Option<&str>) -> Result<String> {
+        let Some(existing) = existing else {
+            return Ok(serde_yaml::to_string(self)?);
+        };
+
+        // Older files are first upgraded in place, then edited like any other
+        let upgraded = Self::upgrade_text(existing);
+        let existing = upgraded.as_deref().unwrap_or(existing);
+        // Ids the file doesn't have yet are added
+        if let Ok((previous, None)) = Self::parse_as_written(existing, ConfigFormat::Yaml) {
+            // Only trust a patch that reads back as exactly this config
+            let patched = yaml_edit::patch(
+                existing,
+                &serde_yaml::to_value(previous)?,
+                &serde_yaml::to_value(self)?,
+                |patched| matches!(Self::parse(patched, ConfigFormat::Yaml), Ok((c, None)) if c == *self),
+            );
+            if let Some(patched) = patched {
+                return Ok(patched);
+            }
+            debug!("Config could not be edited in place; rewriting it");
+        }
+
+        let mut yaml = serde_yaml::to_string(self)?;
+        // Keep the editor schema hint written by `init`
+        if let Some(header) = schema::header_of(existing) {
+            yaml = format!("{}\n{}", header, yaml);
+        }
+        Ok(yaml)
+    }
+
+    /// An older YAML file with its migration applied in place, keeping comments
+    ///
+    /// `None` when the file is current or can't be edited line by line.
+    fn upgrade_text(existing: &str) -> Option<String> {
+        let document = ConfigFormat::Yaml.parse(existing).ok()?;
+        let mut upgraded = document.clone();
+        migration::migrate(&mut upgraded).ok()??;
+        yaml_edit::patch(existing, &document, &upgraded, |patched| {
+            ConfigFormat::Yaml.parse(patched).ok().as_ref() == Some(&upgraded)
+        })
+    }
+
+    /// Load config or create it from the starter template if not exists
     pub fn load_or_default<P: AsRef<Path>>(path: P) -> Result<Self> {
         if path.as_ref().exists() {
             Self::load(path)
         } else {
-            let config = Self::default();
-            config.save(&path)?;
-            Ok(config)
+            let format = ConfigFormat::from_path(path.as_ref())?;
+            let template = templates::find(templates::DEFAULT_TEMPLATE)?;
+            storage::write_with_backup(path.as_ref(), &template.render(format)?)?;
+            template.config()
         }
     }
 
-    /// Validate configuration
-    pub fn validate(&self) -> Result<()> {
+    /// Check the whole configuration and collect every problem with its field path
+    pub fn validate_report(&self) -> ValidationReport {
+        let mut report = ValidationReport::new();
+
         if self.macros.is_empty() {
-            return Err(anyhow::anyhow!("No macros configured"));
+            report.error("macros", "no_macros", "No macros configured");
         }
 
-        // Check for valid action configuration
+        // The engine addresses macros by id; names only matter to extending profiles
+        let mut ids = HashSet::new();
+        let mut names = HashSet::new();
+        for (idx, macro_config) in self.macros.iter().enumerate() {
+            if macro_config.id.is_empty() {
+                report.error(
+                    format!("macros[{}].id", idx),
+                    "empty_id",
+                    format!("Macro #{} has an empty id", idx),
+                );
+            } else if !ids.insert(&macro_config.id) {
+                report.error(
+                    format!("macros[{}].id", idx),
+                    "duplicate_id",
+                    format!("Duplicate macro id: {}", macro_config.id),
+                );
+            }
+            if let Some(name) = &macro_config.name {
+                if !names.insert(name) {
+                    report.warning(
+                        format!("macros[{}].name", idx),
+                        "duplicate_name",
+                        format!(
+                            "Duplicate macro name: {} (extending profiles can only address the first)",
+                            name
+                        ),
+                    );
+                }
+            }
+        }
+
+        // Check for valid action configuration, resolving keys with the engine's parser
         for (idx, macro_config) in self.macros.iter().enumerate() {
             match macro_config.action_type {
+                // The key is looked up in the game's keybinds when the engine starts
+                ActionType::Keyboard if macro_config.game_action.is_some() => {
+                    if macro_config.game_action.as_deref() == Some("") {
+                        report.error(
+                            format!("macros[{}].game_action", idx),
+                            "empty_game_action",
+                            format!("Macro '{}' has an empty game action", macro_config.label()),
+                        );
+                    }
+                }
                 ActionType::Keyboard => {
+                    let path = format!("macros[{}].key", idx);
                     if macro_config.key.is_empty() {
-                        return Err(anyhow::anyhow!("Macro #{} has empty key", idx));
+                        report.error(
+                            path,
+                            "empty_key",
+                            format!("Macro '{}' has empty key", macro_config.label()),
+                        );
+                    } else if keys::parse_key(&macro_config.key).is_none() {
+                        report.error(
+                            path,
+                            "unsupported_key",
+                            format!(
+                                "Macro '{}' has unsupported key '{}' (supported: {})",
+                                macro_config.label(),
+                                macro_config.key,
+                                keys::SUPPORTED_KEYS.join(", ")
+                            ),
+                        );
                     }
                 }
                 ActionType::Mouse => {
                     if macro_config.mouse_button.is_none() {
-                        return Err(anyhow::anyhow!(
-                            "Macro #{} has no mouse button specified",
-                            idx
-                        ));
+                        report.error(
+                            format!("macros[{}].mouse_button", idx),
+                            "missing_mouse_button",
+                            format!(
+                                "Macro '{}' has no mouse button specified",
+                                macro_config.label()
+                            ),
+                        );
                     }
                 }
             }
+
+            // The engine never waits less than 100ms between actions
+            if macro_config.interval_ms < 100 {
+                report.warning(
+                    format!("macros[{}].interval_ms", idx),
+                    "interval_too_short",
+                    format!(
+                        "Macro '{}' interval of {}ms is below the 100ms minimum and will be clamped",
+                        macro_config.label(),
+                        macro_config.interval_ms
+                    ),
+                );
+            }
+            if macro_config.random_variance_ms > macro_config.interval_ms {
+                report.warning(
+                    format!("macros[{}].random_variance_ms", idx),
+                    "variance_exceeds_interval",
+                    format!(
+                        "Macro '{}' variance of {}ms is larger than its {}ms interval",
+                        macro_config.label(),
+                        macro_config.random_variance_ms,
+                        macro_config.interval_ms
+                    ),
+                );
+            }
         }
 
-        // Check for duplicate keys (only for keyboard macros)
-        let mut keys = std::collections::HashSet::new();
-        for macro_config in &self.macros {
-            if macro_config.action_type == ActionType::Keyboard
-                && !macro_config.key.is_empty()
-                && !keys.insert(&macro_config.key)
+        // Check for duplicate keys (only for keyboard macros, "q" and "Q" are the same key)
+        let mut pressed_keys = std::collections::HashMap::new();
+        for (idx, macro_config) in self.macros.iter().enumerate() {
+            if macro_config.action_type != ActionType::Keyboard || macro_config.key.is_empty() {
+                continue;
+            }
+            if let Some(first) =
+                pressed_keys.insert(macro_config.key.to_lowercase(), macro_config.label())
             {
-                return Err(anyhow::anyhow!("Duplicate key: {}", macro_config.key));
+                report.error(
+                    format!("macros[{}].key", idx),
+                    "duplicate_key",
+                    format!(
+                        "Duplicate key: {} (also used by macro '{}')",
+                        macro_config.key, first
+                    ),
+                );
             }
         }
 
+        if self.session.max_runtime_minutes == Some(0) {
+            report.error(
+                "session.max_runtime_minutes",
+                "invalid_session_limit",
+                "Session max runtime must be at least 1 minute",
+            );
+        }
+        if self.session.break_reminder_minutes == Some(0) {
+            report.error(
+                "session.break_reminder_minutes",
+                "invalid_session_limit",
+                "Session break reminder interval must be at least 1 minute",
+            );
+        }
+
         // Check for duplicate hotkeys
-        let mut hotkeys = std::collections::HashSet::new();
-        for macro_config in &self.macros {
+        let mut hotkeys = std::collections::HashMap::new();
+        for (idx, macro_config) in self.macros.iter().enumerate() {
+            let path = format!("macros[{}].toggle_hotkey", idx);
             if macro_config.toggle_hotkey.is_empty() {
-                return Err(anyhow::anyhow!("Macro has empty hotkey"));
+                report.error(
+                    path,
+                    "empty_hotkey",
+                    format!("Macro '{}' has empty hotkey", macro_config.label()),
+                );
+            } else if keys::parse_hotkey(&macro_config.toggle_hotkey).is_none() {
+                report.error(
+                    path,
+                    "unsupported_hotkey",
+                    format!(
+                        "Macro '{}' has unsupported toggle hotkey '{}' (supported: {})",
+                        macro_config.label(),
+                        macro_config.toggle_hotkey,
+                        keys::SUPPORTED_HOTKEYS.join(", ")
+                    ),
+                );
+            } else if let Some(first) =
+                hotkeys.insert(&macro_config.toggle_hotkey, macro_config.label())
+            {
+                report.error(
+                    path,
+                    "duplicate_hotkey",
+                    format!(
+                        "Duplicate hotkey: {} (also used by macro '{}')",
+                        macro_config.toggle_hotkey, first
+                    ),
+                );
             }
-            if !hotkeys.insert(&macro_config.toggle_hotkey) {
-                return Err(anyhow::anyhow!(
-                    "Duplicate hotkey: {}",
-                    macro_config.toggle_hotkey
-                ));
+        }
+
+        if let Some(cycle_hotkey) = &self.profile_cycle_hotkey {
+            if keys::parse_hotkey(cycle_hotkey).is_none() {
+                report.error(
+                    "profile_cycle_hotkey",
+                    "unsupported_hotkey",
+                    format!(
+                        "Profile cycle hotkey '{}' is not supported (supported: {})",
+                        cycle_hotkey,
+                        keys::SUPPORTED_HOTKEYS.join(", ")
+                    ),
+                );
+            } else if let Some(other) = self
+                .macros
+                .iter()
+                .find(|m| &m.toggle_hotkey == cycle_hotkey)
+            {
+                report.error(
+                    "profile_cycle_hotkey",
+                    "duplicate_hotkey",
+                    format!(
+                        "Profile cycle hotkey {} is also the toggle hotkey of macro '{}'",
+                        cycle_hotkey,
+                        other.label()
+                    ),
+                );
             }
         }
 
-        Ok(())
+        report
+    }
+}
+
+/// Checksum of a macro's settings, for ids of unnamed macros
+fn fingerprint(macro_config: &KeyMacro) -> u32 {
+    let mut crc = flate2::Crc::new();
+    let settings = KeyMacro {
+        id: String::new(),
+        ..macro_config.clone()
+    };
+    crc.update(
+        serde_json::to_string(&settings)
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    crc.sum()
+}
+
+/// Lowercase `name` with every run of other characters turned into one `-`
+pub fn slug(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1_CONFIG: &str = "# Old config without a version\nmacros:\n  - key: \"1\"\n    interval_ms: 1000\n    random_variance_ms: 200\n    toggle_hotkey: \"F9\"\n";
+
+    #[test]
+    fn loading_an_old_config_leaves_the_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, V1_CONFIG).unwrap();
+
+        let (config, report) = Config::load_with_report(&path).unwrap();
+
+        assert_eq!(config.macros.len(), 1);
+        assert_eq!(report.migration.as_ref().unwrap().from_version, 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), V1_CONFIG);
+        assert!(storage::list_backups(&path).unwrap().is_empty());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn persisting_a_migration_backs_up_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, V1_CONFIG).unwrap();
+
+        let (config, mut report) = Config::load_with_report(&path).unwrap();
+        config.persist_load(&path, &mut report).unwrap();
+
+        let backups = storage::list_backups(&path).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), V1_CONFIG);
+        assert_eq!(
+            report.migration.unwrap().backup_path,
+            Some(backups[0].display().to_string())
+        );
+        let (_, report) = Config::load_with_report(&path).unwrap();
+        assert!(report.migration.is_none());
+    }
+
+    #[test]
+    fn saving_an_upgraded_config_keeps_its_comments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, V1_CONFIG).unwrap();
+
+        let (config, mut report) = Config::load_with_report(&path).unwrap();
+        config.persist_load(&path, &mut report).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Old config without a version\nmacros:\n  - key: \"1\"\n    interval_ms: 1000\n    random_variance_ms: 200\n    toggle_hotkey: \"F9\"\n    action_type: keyboard\n    id: macro-96cbf4df\nversion: 2\n"
+        );
+    }
+
+    fn unnamed(key: &str) -> KeyMacro {
+        let yaml = format!("key: \"{}\"\ninterval_ms: 1000\ntoggle_hotkey: F9\n", key);
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    #[test]
+    fn unnamed_macro_ids_do_not_follow_the_position() {
+        let mut config = Config {
+            macros: vec![unnamed("1"), unnamed("2"), unnamed("1")],
+            ..Config::default()
+        };
+        config.assign_ids();
+        let ids: Vec<String> = config.macros.iter().map(|m| m.id.clone()).collect();
+        assert!(ids[0].starts_with("macro-") && ids[0] != ids[1]);
+        assert_eq!(ids[2], format!("{}-2", ids[0]));
+
+        let mut shorter = Config {
+            macros: vec![unnamed("2")],
+            ..Config::default()
+        };
+        shorter.assign_ids();
+        assert_eq!(shorter.macros[0].id, ids[1]);
+    }
+
+    #[test]
+    fn ids_are_written_on_the_first_save_and_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, "version: 2\nmacros:\n  # Flask\n  - name: Life Flask\n    key: \"1\"\n    interval_ms: 1000\n    toggle_hotkey: F9\n").unwrap();
+
+        let mut config = Config::load(&path).unwrap();
+        assert_eq!(config.macros[0].id, "life-flask");
+        config.save(&path).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("  # Flask\n") && saved.contains("    id: life-flask\n"));
+
+        // A renamed macro keeps its id
+        config.macros[0].name = Some("Mana Flask".to_string());
+        config.save(&path).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert_eq!(saved.matches("id: life-flask").count(), 1, "{}", saved);
+        assert_eq!(Config::load(&path).unwrap(), config);
+    }
+
+    #[test]
+    fn corrupt_config_is_restored_only_when_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, V1_CONFIG).unwrap();
+        let (config, mut report) = Config::load_with_report(&path).unwrap();
+        config.persist_load(&path, &mut report).unwrap();
+        let good = fs::read_to_string(&path).unwrap();
+        config.save(&path).unwrap();
+        fs::write(&path, "macros: [unclosed").unwrap();
+
+        let (config, mut report) = Config::load_with_report(&path).unwrap();
+        assert!(report.restored_backup.is_some());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "macros: [unclosed");
+
+        config.persist_load(&path, &mut report).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), good);
     }
 }
@@ -0,0 +1,493 @@
+//! Control protocol for a running engine
+//!
+//! The daemon speaks JSON-RPC 2.0 over a [`Transport`], one JSON object per
+//! line in each direction, so scripts and window-manager bindings can drive
+//! it with nothing more than `socat`:
+//!
+//! ```text
+//! {"jsonrpc":"2.0","id":1,"method":"toggle","params":{"id":"life_flask"}}
+//! ```
+//!
+//! Methods:
+//! - `status`: whether the engine runs or is paused, the active profile, the
+//!   macros and the session
+//! - `toggle {id}`, `enable {id?}`, `disable {id?}`: change one macro, or
+//!   every macro when `enable`/`disable` get no id
+//! - `pause`, `resume`: hold fire on every macro without changing which are enabled
+//! - `switch_profile {name}`, `cycle_profile`: change the active profile
+//! - `reload`: apply the active profile's file again
+//! - `stop`: stop the engine, which ends the daemon
+//!
+//! Every method but `reload`, `cycle_profile` and `stop` answers with the
+//! new `status`.
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+use crate::ipc::Transport;
+use crate::macro_engine::{MacroEngineState, MacroStatus};
+use crate::session::SessionStatus;
+
+const JSONRPC_VERSION: &str = "2.0";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The engine refused the request, e.g. an unknown macro id
+const ENGINE_ERROR: i64 = -32000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    /// Absent for notifications, which get no response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(error: anyhow::Error) -> Self {
+        Self::new(ENGINE_ERROR, error.to_string())
+    }
+}
+
+/// What `status` reports
+#[derive(Debug, Serialize)]
+pub struct DaemonStatus {
+    pub running: bool,
+    pub paused: bool,
+    pub profile: String,
+    pub macros: Vec<MacroStatus>,
+    pub session: SessionStatus,
+}
+
+#[derive(Deserialize)]
+struct MacroParams {
+    id: String,
+}
+
+#[derive(Default, Deserialize)]
+struct OptionalMacroParams {
+    id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProfileParams {
+    name: String,
+}
+
+/// Answer clients of `listener` until the engine stops or accepting fails
+///
+/// Each client gets its own task and may send any number of requests. Once
+/// the engine stops, every task is waited for so the request that stopped it
+/// still gets its answer before the daemon exits.
+pub async fn serve<T: Transport>(mut listener: T, engine: MacroEngineState) -> Result<()> {
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            connection = listener.accept() => {
+                let connection = connection?;
+                let engine = engine.clone();
+                connections.spawn(async move {
+                    if let Err(e) = handle_connection(connection, &engine).await {
+                        debug!("Control connection closed: {}", e);
+                    }
+                });
+            }
+            // Reap finished clients so a long-lived daemon doesn't collect them
+            Some(_) = connections.join_next() => {}
+            _ = engine.stopped() => break,
+        }
+    }
+    while connections.join_next().await.is_some() {}
+    Ok(())
+}
+
+async fn handle_connection<S>(connection: S, engine: &MacroEngineState) -> Result<()>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(connection);
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        // Idle clients must not keep a stopped daemon alive
+        let line = tokio::select! {
+            line = lines.next_line() => line?,
+            _ = engine.stopped() => None,
+        };
+        let Some(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_line(engine, &line).await {
+            let mut json = serde_json::to_string(&response)?;
+            json.push('\n');
+            writer.write_all(json.as_bytes()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Response to one line from a client, if it wants one
+async fn handle_line(engine: &MacroEngineState, line: &str) -> Option<Response> {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, e.to_string())),
+    };
+    let id = value.get("id").cloned();
+    let request = match serde_json::from_value::<Request>(value) {
+        Ok(request) if request.jsonrpc == JSONRPC_VERSION => request,
+        Ok(_) => {
+            return Some(error_response(
+                id.unwrap_or_default(),
+                INVALID_REQUEST,
+                "jsonrpc must be \"2.0\"",
+            ))
+        }
+        Err(e) => {
+            return Some(error_response(
+                id.unwrap_or_default(),
+                INVALID_REQUEST,
+                e.to_string(),
+            ))
+        }
+    };
+
+    debug!("Control request: {}", request.method);
+    let outcome = dispatch(engine, &request.method, request.params).await;
+    if let Err(e) = &outcome {
+        warn!("Control request '{}' failed: {}", request.method, e.message);
+    }
+    let id = request.id?;
+    Some(match outcome {
+        Ok(result) => Response {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        },
+        Err(error) => Response {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: None,
+            error: Some(error),
+        },
+    })
+}
+
+async fn dispatch(
+    engine: &MacroEngineState,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    match method {
+        "status" => {}
+        "toggle" => {
+            let MacroParams { id } = parse_params(params)?;
+            engine.toggle_macro(&id).await?;
+        }
+        "enable" | "disable" => {
+            let OptionalMacroParams { id } = parse_params(params)?;
+            engine
+                .set_enabled(id.as_deref(), method == "enable")
+                .await?;
+        }
+        "pause" => engine.set_paused(true),
+        "resume" => engine.set_paused(false),
+        "switch_profile" => {
+            let ProfileParams { name } = parse_params(params)?;
+            engine.switch_profile(&name).await?;
+            info!("Switched to profile '{}' on request", name);
+        }
+        "cycle_profile" => return Ok(Value::String(engine.cycle_profile().await?)),
+        "reload" => return to_value(engine.reload().await?),
+        "stop" => {
+            info!("Stopping on request");
+            engine.stop().await;
+            return Ok(Value::Null);
+        }
+        _ => {
+            return Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method '{}'", method),
+            ))
+        }
+    }
+    to_value(status(engine).await)
+}
+
+async fn status(engine: &MacroEngineState) -> DaemonStatus {
+    DaemonStatus {
+        running: engine.is_running(),
+        paused: engine.is_paused(),
+        profile: engine.profiles().active(),
+        macros: engine.get_status().await,
+        session: engine.get_session_status().await,
+    }
+}
+
+/// Parameters of a method; missing parameters read as an empty object
+fn parse_params<P: DeserializeOwned>(params: Value) -> Result<P, RpcError> {
+    let params = match params {
+        Value::Null => Value::Object(Default::default()),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value(result: impl Serialize) -> Result<Value, RpcError> {
+    serde_json::to_value(result).map_err(|e| RpcError::from(anyhow::Error::from(e)))
+}
+
+fn error_response(id: Value, code: i64, message: impl Into<String>) -> Response {
+    Response {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        id,
+        result: None,
+        error: Some(RpcError::new(code, message)),
+    }
+}
+
+/// Send one request to the daemon listening at `endpoint` and return its result
+pub async fn call<T: Transport>(endpoint: &Path, method: &str, params: Value) -> Result<Value> {
+    let connection = T::connect(endpoint).await.map_err(|e| {
+        anyhow::anyhow!(
+            "No daemon is listening on {} ({}); start one with `daemon`",
+            endpoint.display(),
+            e
+        )
+    })?;
+    let (reader, mut writer) = tokio::io::split(connection);
+
+    let request = Request {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        id: Some(Value::from(1)),
+        method: method.to_string(),
+        params,
+    };
+    let mut json = serde_json::to_string(&request)?;
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("The daemon closed the connection without answering"))?;
+    let response: Response = serde_json::from_str(&line)?;
+    match response.error {
+        Some(error) => Err(anyhow::anyhow!("{} (code {})", error.message, error.code)),
+        None => Ok(response.result.unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overrides::Overrides;
+    use crate::profiles::ProfileStore;
+
+    fn engine() -> (tempfile::TempDir, MacroEngineState) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(
+            &path,
+            "version: 2\nmacros:\n  - id: flask\n    key: '1'\n    interval_ms: 1000\n    toggle_hotkey: F9\n",
+        )
+        .unwrap();
+        let engine = MacroEngineState::new(ProfileStore::open(&path), Overrides::default());
+        (dir, engine)
+    }
+
+    async fn error_of(engine: &MacroEngineState, line: &str) -> (Value, i64) {
+        let response = handle_line(engine, line).await.unwrap();
+        assert!(response.result.is_none());
+        (response.id, response.error.unwrap().code)
+    }
+
+    #[tokio::test]
+    async fn malformed_lines_are_parse_errors() {
+        let (_dir, engine) = engine();
+        assert_eq!(
+            error_of(&engine, "{\"jsonrpc\": \"2.0\",").await,
+            (Value::Null, PARSE_ERROR)
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_need_version_2_and_a_method() {
+        let (_dir, engine) = engine();
+        assert_eq!(
+            error_of(&engine, r#"{"jsonrpc":"1.0","id":3,"method":"status"}"#).await,
+            (Value::from(3), INVALID_REQUEST)
+        );
+        assert_eq!(
+            error_of(&engine, r#"{"jsonrpc":"2.0","id":"a"}"#).await,
+            (Value::from("a"), INVALID_REQUEST)
+        );
+        assert_eq!(
+            error_of(&engine, r#"{"jsonrpc":"2.0","id":4,"method":"jump"}"#).await,
+            (Value::from(4), METHOD_NOT_FOUND)
+        );
+    }
+
+    #[tokio::test]
+    async fn notifications_are_carried_out_without_an_answer() {
+        let (_dir, engine) = engine();
+        assert!(
+            handle_line(&engine, r#"{"jsonrpc":"2.0","method":"pause"}"#)
+                .await
+                .is_none()
+        );
+        assert!(engine.is_paused());
+
+        // Failed notifications stay silent too
+        assert!(
+            handle_line(&engine, r#"{"jsonrpc":"2.0","method":"toggle"}"#)
+                .await
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn wrong_params_are_invalid_params() {
+        let (_dir, engine) = engine();
+        assert_eq!(
+            error_of(&engine, r#"{"jsonrpc":"2.0","id":5,"method":"toggle"}"#).await,
+            (Value::from(5), INVALID_PARAMS)
+        );
+        assert_eq!(
+            error_of(
+                &engine,
+                r#"{"jsonrpc":"2.0","id":6,"method":"switch_profile","params":{"name":7}}"#
+            )
+            .await,
+            (Value::from(6), INVALID_PARAMS)
+        );
+    }
+
+    #[tokio::test]
+    async fn status_answers_with_the_request_id() {
+        let (_dir, engine) = engine();
+        let response = handle_line(&engine, r#"{"jsonrpc":"2.0","id":7,"method":"status"}"#)
+            .await
+            .unwrap();
+        assert_eq!(response.id, Value::from(7));
+        let status = response.result.unwrap();
+        assert_eq!(status["running"], Value::Bool(false));
+        assert_eq!(status["profile"], Value::from("default"));
+    }
+
+    #[tokio::test]
+    async fn serving_ends_with_the_engine() {
+        struct Idle;
+        impl Transport for Idle {
+            type Connection = tokio::io::DuplexStream;
+            type Client = tokio::io::DuplexStream;
+            fn bind(_: &Path) -> std::io::Result<Self> {
+                Ok(Self)
+            }
+            async fn accept(&mut self) -> std::io::Result<Self::Connection> {
+                std::future::pending().await
+            }
+            async fn connect(_: &Path) -> std::io::Result<Self::Client> {
+                unreachable!()
+            }
+        }
+
+        let (_dir, engine) = engine();
+        tokio::time::timeout(std::time::Duration::from_secs(5), serve(Idle, engine))
+            .await
+            .unwrap()
+            .unwrap();
+    }
+    #[tokio::test]
+    async fn stop_is_answered_before_serving_ends() {
+        /// Hands out one connection, then waits forever
+        struct Single(Option<tokio::io::DuplexStream>);
+        impl Transport for Single {
+            type Connection = tokio::io::DuplexStream;
+            type Client = tokio::io::DuplexStream;
+            fn bind(_: &Path) -> std::io::Result<Self> {
+                unreachable!()
+            }
+            async fn accept(&mut self) -> std::io::Result<Self::Connection> {
+                match self.0.take() {
+                    Some(connection) => Ok(connection),
+                    None => std::future::pending().await,
+                }
+            }
+            async fn connect(_: &Path) -> std::io::Result<Self::Client> {
+                unreachable!()
+            }
+        }
+
+        let (_dir, engine) = engine();
+        let config = engine.profiles().load("default").unwrap();
+        engine.start(config).await.unwrap();
+        let (client, server) = tokio::io::duplex(4096);
+        let (reader, mut writer) = tokio::io::split(client);
+        writer
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":9,\"method\":\"stop\"}\n")
+            .await
+            .unwrap();
+
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            serve(Single(Some(server)), engine.clone()),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(!engine.is_running());
+
+        // The answer is already there, not still being written
+        let line = tokio::time::timeout(
+            std::time::Duration::ZERO,
+            BufReader::new(reader).lines().next_line(),
+        )
+        .await
+        .expect("stop was not answered before serving ended")
+        .unwrap()
+        .unwrap();
+        let response: Response = serde_json::from_str(&line).unwrap();
+        assert_eq!(response.id, Value::from(9));
+        assert!(response.error.is_none());
+    }
+}
@@ -0,0 +1,431 @@
+//! What changed between two configs, in terms of macros and settings
+//!
+//! Text diffs of config files are noisy: reordering, comments, durations
+//! written as `1.5s` and derived ids all show up. This compares the parsed
+//! configs instead, matching macros by id or by the key they press, and
+//! reports fields the way the GUI names them, e.g. `interval 1000→900ms`.
+
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+
+use crate::config::{ActionType, Config, KeyMacro};
+use crate::format;
+use crate::macro_engine::MacroEngineState;
+
+/// Top-level fields that are not settings of the profile itself
+const IGNORED_KEYS: &[&str] = &["version", "macros", "active_profile"];
+
+/// How a macro differs between the two configs
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MacroChange {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One field with a different value, e.g. `toggle_hotkey` from `F9` to `F10`
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    /// Field path, e.g. `toggle_hotkey` or `session.max_runtime_minutes`
+    pub field: String,
+    /// Name shown to the user, e.g. `hotkey`
+    pub label: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    /// Unit of both values, e.g. `ms`, shown once after the new value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<&'static str>,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let before = self.before.as_deref().unwrap_or("none");
+        let after = self.after.as_deref().unwrap_or("none");
+        write!(f, "{} {}→{}", self.label, before, after)?;
+        if let (Some(unit), Some(_)) = (self.unit, &self.after) {
+            write!(f, "{}", unit)?;
+        }
+        Ok(())
+    }
+}
+
+/// A macro that was added, removed or changed
+#[derive(Debug, Clone, Serialize)]
+pub struct MacroDiff {
+    /// Id in the newer config, or in the older one for removed macros
+    pub id: String,
+    /// Name, or id without one
+    pub label: String,
+    pub change: MacroChange,
+    /// Changed fields; empty for added and removed macros
+    pub fields: Vec<FieldChange>,
+    /// Summary of an added or removed macro
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+impl fmt::Display for MacroDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (marker, detail) = match self.change {
+            MacroChange::Added => ('+', self.summary.clone().unwrap_or_default()),
+            MacroChange::Removed => ('-', self.summary.clone().unwrap_or_default()),
+            MacroChange::Changed => (
+                '~',
+                self.fields
+                    .iter()
+                    .map(FieldChange::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        };
+        write!(f, "{} Macro '{}': {}", marker, self.label, detail)
+    }
+}
+
+/// Every difference between two configs
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ConfigDiff {
+    /// In the order of the newer config, removed macros last
+    pub macros: Vec<MacroDiff>,
+    /// Changed settings outside the macros, e.g. session limits
+    pub settings: Vec<FieldChange>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.macros.is_empty() && self.settings.is_empty()
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        for entry in &self.macros {
+            writeln!(f, "{}", entry)?;
+        }
+        for setting in &self.settings {
+            writeln!(f, "~ {}", setting)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare two configs, `before` being the older one
+pub fn diff(before: &Config, after: &Config) -> ConfigDiff {
+    let pairs = match_macros(before, after);
+    let stored_before = before.without_derived_ids();
+    let stored_after = after.without_derived_ids();
+
+    let mut macros = Vec::new();
+    let mut matched = vec![false; before.macros.len()];
+    for (idx, new) in after.macros.iter().enumerate() {
+        let Some(old_idx) = pairs[idx] else {
+            macros.push(MacroDiff {
+                id: new.id.clone(),
+                label: new.label().to_string(),
+                change: MacroChange::Added,
+                fields: Vec::new(),
+                summary: Some(MacroEngineState::describe(new)),
+            });
+            continue;
+        };
+        matched[old_idx] = true;
+
+        let old = &before.macros[old_idx];
+        let mut fields = Vec::new();
+        // Derived ids change with an unnamed macro's settings, so only ids someone wrote count
+        let explicit =
+            !stored_before.macros[old_idx].id.is_empty() || !stored_after.macros[idx].id.is_empty();
+        if explicit && old.id != new.id {
+            fields.push(change("id", Some(&old.id), Some(&new.id)));
+        }
+        compare(
+            "",
+            &to_mapping(&without_id(old)),
+            &to_mapping(&without_id(new)),
+            &mut fields,
+        );
+        if !fields.is_empty() {
+            macros.push(MacroDiff {
+                id: new.id.clone(),
+                label: new.label().to_string(),
+                change: MacroChange::Changed,
+                fields,
+                summary: None,
+            });
+        }
+    }
+
+    for (old, _) in before.macros.iter().zip(&matched).filter(|(_, m)| !**m) {
+        macros.push(MacroDiff {
+            id: old.id.clone(),
+            label: old.label().to_string(),
+            change: MacroChange::Removed,
+            fields: Vec::new(),
+            summary: Some(MacroEngineState::describe(old)),
+        });
+    }
+
+    let mut settings = Vec::new();
+    let mut before_settings = to_mapping(before);
+    let mut after_settings = to_mapping(after);
+    for key in IGNORED_KEYS {
+        before_settings.remove(*key);
+        after_settings.remove(*key);
+    }
+    compare("", &before_settings, &after_settings, &mut settings);
+
+    ConfigDiff { macros, settings }
+}
+
+/// For each macro of `after`, the macro of `before` it corresponds to
+///
+/// Pairs are found in rounds, most certain first: same id and key, same
+/// written id or name, same key, and finally same position for the same
+/// kind of action. Each macro is used at most once.
+fn match_macros(before: &Config, after: &Config) -> Vec<Option<usize>> {
+    let stored_before = before.without_derived_ids();
+    let stored_after = after.without_derived_ids();
+    let written_id = |config: &Config, idx: usize| {
+        let id = &config.macros[idx].id;
+        (!id.is_empty()).then_some(id.clone())
+    };
+
+    type Rule<'a> = Box<dyn Fn(usize, usize) -> bool + 'a>;
+    let rules: [Rule; 4] = [
+        Box::new(|old, new| {
+            before.macros[old].id == after.macros[new].id
+                && action(&before.macros[old]) == action(&after.macros[new])
+        }),
+        Box::new(|old, new| {
+            let same_id = written_id(&stored_before, old)
+                .is_some_and(|id| written_id(&stored_after, new) == Some(id));
+            let same_name = before.macros[old].name.is_some()
+                && before.macros[old].name == after.macros[new].name;
+            same_id || same_name
+        }),
+        Box::new(|old, new| action(&before.macros[old]) == action(&after.macros[new])),
+        Box::new(|old, new| {
+            old == new && before.macros[old].action_type == after.macros[new].action_type
+        }),
+    ];
+
+    let mut pairs = vec![None; after.macros.len()];
+    let mut used = vec![false; before.macros.len()];
+    for rule in &rules {
+        for (new, pair) in pairs.iter_mut().enumerate() {
+            if pair.is_some() {
+                continue;
+            }
+            if let Some(old) = (0..before.macros.len()).find(|&old| !used[old] && rule(old, new)) {
+                used[old] = true;
+                *pair = Some(old);
+            }
+        }
+    }
+    pairs
+}
+
+/// What a macro presses, e.g. `key:q`, `mouse:left` or `game:flask_1`
+fn action(macro_config: &KeyMacro) -> String {
+    if let Some(game_action) = &macro_config.game_action {
+        return format!("game:{}", game_action);
+    }
+    match macro_config.action_type {
+        ActionType::Keyboard => format!("key:{}", macro_config.key.to_lowercase()),
+        ActionType::Mouse => format!(
+            "mouse:{}",
+            macro_config
+                .mouse_button
+                .as_ref()
+                .map(|b| format::one_line(&serde_yaml::to_value(b).unwrap_or_default()))
+                .unwrap_or_default()
+        ),
+    }
+}
+
+fn without_id(macro_config: &KeyMacro) -> KeyMacro {
+    KeyMacro {
+        id: String::new(),
+        ..macro_config.clone()
+    }
+}
+
+fn to_mapping<T: Serialize>(value: &T) -> Mapping {
+    match serde_yaml::to_value(value) {
+        Ok(Value::Mapping(mapping)) => mapping,
+        _ => Mapping::new(),
+    }
+}
+
+/// Collect differing fields of two mappings, descending into nested ones
+fn compare(prefix: &str, before: &Mapping, after: &Mapping, changes: &mut Vec<FieldChange>) {
+    let mut keys: Vec<&Value> = after.keys().collect();
+    keys.extend(before.keys().filter(|key| !after.contains_key(*key)));
+
+    for key in keys {
+        let Some(name) = key.as_str() else {
+            continue;
+        };
+        let field = format!("{}{}", prefix, name);
+        let old = before.get(key).filter(|v| is_set(v));
+        let new = after.get(key).filter(|v| is_set(v));
+        match (old, new) {
+            (Some(Value::Mapping(old)), Some(Value::Mapping(new))) => {
+                compare(&format!("{}.", field), old, new, changes);
+            }
+            _ if old == new => {}
+            _ => changes.push(change(
+                &field,
+                old.map(format::one_line).as_deref(),
+                new.map(format::one_line).as_deref(),
+            )),
+        }
+    }
+}
+
+/// Unset optional fields and empty keys both read as "none"
+fn is_set(value: &Value) -> bool {
+    !value.is_null() && value.as_str() != Some("")
+}
+
+fn change(field: &str, before: Option<&str>, after: Option<&str>) -> FieldChange {
+    let (label, unit) = match field {
+        "interval_ms" => ("interval", Some("ms")),
+        "random_variance_ms" => ("variance", Some("ms")),
+        "toggle_hotkey" => ("hotkey", None),
+        "enabled_by_default" => ("enabled by default", None),
+        "action_type" => ("action", None),
+        "mouse_button" => ("button", None),
+        "game_action" => ("game action", None),
+        "session.max_runtime_minutes" => ("max runtime", Some(" min")),
+        "session.break_reminder_minutes" => ("break reminder", Some(" min")),
+        "profile_cycle_hotkey" => ("profile cycle hotkey", None),
+        "game_config" => ("game config", None),
+        other => (other, None),
+    };
+    FieldChange {
+        field: field.to_string(),
+        label: label.to_string(),
+        before: before.map(str::to_string),
+        after: after.map(str::to_string),
+        unit,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::ConfigFormat;
+
+    fn config(yaml: &str) -> Config {
+        Config::parse(yaml, ConfigFormat::Yaml).unwrap().0
+    }
+
+    fn entry(fields: &str) -> String {
+        format!("  - interval_ms: 1000\n    toggle_hotkey: F9\n{}", fields)
+    }
+
+    fn changes(diff: &ConfigDiff) -> Vec<String> {
+        diff.macros.iter().map(MacroDiff::to_string).collect()
+    }
+
+    #[test]
+    fn reordered_macros_are_unchanged() {
+        let flask = entry("    key: \"1\"\n");
+        let skill = entry("    key: e\n");
+        let before = config(&format!("macros:\n{}{}", flask, skill));
+        let after = config(&format!("macros:\n{}{}", skill, flask));
+        assert_eq!(match_macros(&before, &after), vec![Some(1), Some(0)]);
+        assert!(diff(&before, &after).is_empty());
+    }
+
+    #[test]
+    fn renamed_macros_match_by_written_id() {
+        let before = config(&format!(
+            "macros:\n{}",
+            entry("    id: flask\n    name: Life\n    key: \"1\"\n")
+        ));
+        let after = config(&format!(
+            "macros:\n{}",
+            entry("    id: flask\n    name: Mana\n    key: \"2\"\n")
+        ));
+        assert_eq!(
+            changes(&diff(&before, &after)),
+            vec!["~ Macro 'Mana': name Life→Mana, key 1→2"]
+        );
+    }
+
+    #[test]
+    fn named_macros_match_by_name_when_the_key_changes() {
+        let before = config(&format!(
+            "macros:\n{}{}",
+            entry("    key: \"1\"\n"),
+            entry("    name: Flask\n    key: \"2\"\n")
+        ));
+        let after = config(&format!(
+            "macros:\n{}{}",
+            entry("    name: Flask\n    key: \"3\"\n"),
+            entry("    key: \"1\"\n")
+        ));
+        assert_eq!(match_macros(&before, &after), vec![Some(1), Some(0)]);
+        assert_eq!(
+            changes(&diff(&before, &after)),
+            vec!["~ Macro 'Flask': key 2→3"]
+        );
+    }
+
+    #[test]
+    fn unnamed_macros_fall_back_to_their_position() {
+        let before = config(&format!(
+            "macros:\n{}{}",
+            entry("    key: \"1\"\n"),
+            entry("    key: \"2\"\n")
+        ));
+        let after = config(&format!(
+            "macros:\n{}{}",
+            entry("    key: \"3\"\n"),
+            entry("    key: \"2\"\n")
+        ));
+        assert_eq!(match_macros(&before, &after), vec![Some(0), Some(1)]);
+        let changed = diff(&before, &after);
+        assert_eq!(changed.macros.len(), 1);
+        assert_eq!(changed.macros[0].change, MacroChange::Changed);
+        // The derived id changed with the key, but nobody wrote it
+        assert_eq!(changed.macros[0].fields.len(), 1);
+        assert_eq!(changed.macros[0].fields[0].to_string(), "key 1→3");
+
+        // A mouse macro in that spot is a different macro
+        let mouse = config(&format!(
+            "macros:\n{}{}",
+            entry("    action_type: mouse\n    mouse_button: left\n"),
+            entry("    key: \"2\"\n")
+        ));
+        let kinds: Vec<MacroChange> = diff(&before, &mouse)
+            .macros
+            .iter()
+            .map(|m| m.change)
+            .collect();
+        assert_eq!(kinds, vec![MacroChange::Added, MacroChange::Removed]);
+    }
+
+    #[test]
+    fn settings_are_compared_field_by_field() {
+        let before = config("macros: []\nsession:\n  max_runtime_minutes: 60\n");
+        let after =
+            config("macros: []\nsession:\n  max_runtime_minutes: 90\nprofile_cycle_hotkey: F8\n");
+        let settings: Vec<String> = diff(&before, &after)
+            .settings
+            .iter()
+            .map(FieldChange::to_string)
+            .collect();
+        assert_eq!(
+            settings,
+            vec!["profile cycle hotkey none→F8", "max runtime 60→90 min"]
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::de::{self, Deserializer, Visitor};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fmt;
+
+/// Regex for a duration string, mirroring `parse_millis`
+const DURATION_PATTERN: &str = r"^\s*\d+(\.\d+)?\s*(ms|s|m)?\s*$";
+
+/// Regex for an interval string: a duration or a `<min>..<max>` range
+const INTERVAL_PATTERN: &str =
+    r"^\s*\d+(\.\d+)?\s*(ms|s|m)?\s*(\.\.\s*\d+(\.\d+)?\s*(ms|s|m)?\s*)?$";
+
+/// A duration in milliseconds, written as a number (`1500`) or a string with
+/// a unit (`"1500ms"`, `"1.5s"`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Millis(pub u64);
+
+/// An interval written as one duration or as a `"<min>..<max>"` range
+///
+/// Ranges map onto a base interval in the middle and a ± variance reaching
+/// both ends, e.g. `"1.2s..1.8s"` is 1500ms ± 300ms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interval {
+    Fixed(u64),
+    Range { base: u64, variance: u64 },
+}
+
+/// Parse a duration such as `"800ms"`, `"1.5s"` or `"1200"` into milliseconds
+///
+/// The number is read as exact decimal digits, so `"2.01s"` is 2010ms.
+pub fn parse_millis(text: &str) -> Result<u64, String> {
+    let trimmed = text.trim();
+    let (number, scale) = if let Some(number) = trimmed.strip_suffix("ms") {
+        (number, 1)
+    } else if let Some(number) = trimmed.strip_suffix('s') {
+        (number, 1000)
+    } else if let Some(number) = trimmed.strip_suffix('m') {
+        (number, 60_000)
+    } else {
+        (trimmed, 1)
+    };
+
+    let number = number.trim();
+    if number.starts_with('-') {
+        return Err(format!(
+            "invalid duration \"{}\": must not be negative",
+            text
+        ));
+    }
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(whole) || (number.contains('.') && !is_digits(fraction)) {
+        return Err(format!(
+            "invalid duration \"{}\": expected a number with an optional unit (ms, s or m), e.g. \"800ms\" or \"1.5s\"",
+            text
+        ));
+    }
+
+    let too_long = || format!("invalid duration \"{}\": too long", text);
+    let fraction = fraction.trim_end_matches('0');
+    let whole: u128 = whole.parse().map_err(|_| too_long())?;
+    let numerator: u128 = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse().map_err(|_| too_long())?
+    };
+    let denominator = u32::try_from(fraction.len())
+        .ok()
+        .and_then(|len| 10u128.checked_pow(len))
+        .ok_or_else(too_long)?;
+
+    let fraction_millis = numerator.checked_mul(scale).ok_or_else(too_long)?;
+    if fraction_millis % denominator > 0 {
+        return Err(format!(
+            "invalid duration \"{}\": must be a whole number of milliseconds",
+            text
+        ));
+    }
+    whole
+        .checked_mul(scale)
+        .and_then(|millis| millis.checked_add(fraction_millis / denominator))
+        .and_then(|millis| u64::try_from(millis).ok())
+        .ok_or_else(too_long)
+}
+
+/// Parse an interval: a single duration or a `"<min>..<max>"` range
+pub fn parse_interval(text: &str) -> Result<Interval, String> {
+    let Some((min, max)) = text.split_once("..") else {
+        return parse_millis(text).map(Interval::Fixed);
+    };
+
+    let min = parse_millis(min).map_err(|e| format!("range \"{}\": {}", text, e))?;
+    let max = parse_millis(max).map_err(|e| format!("range \"{}\": {}", text, e))?;
+    if min > max {
+        return Err(format!(
+            "invalid range \"{}\": the minimum ({}ms) is larger than the maximum ({}ms)",
+            text, min, max
+        ));
+    }
+    if (max - min) % 2 == 1 {
+        return Err(format!(
+            "invalid range \"{}\": {}ms wide, which has no whole-millisecond middle; make it an even number of milliseconds wide",
+            text,
+            max - min
+        ));
+    }
+    Ok(Interval::Range {
+        base: (min + max) / 2,
+        variance: (max - min) / 2,
+    })
+}
+
+/// Accepts unsigned integers as milliseconds and strings via `parse`
+struct TextOrMillis<T> {
+    expecting: &'static str,
+    parse: fn(&str) -> Result<T, String>,
+    from_millis: fn(u64) -> T,
+}
+
+impl<T> Visitor<'_> for TextOrMillis<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.expecting)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<T, E> {
+        Ok((self.from_millis)(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<T, E> {
+        u64::try_from(value)
+            .map(self.from_millis)
+            .map_err(|_| E::custom(format!("invalid duration {}: must not be negative", value)))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<T, E> {
+        Err(E::custom(format!(
+            "invalid duration {}: use whole milliseconds or a unit, e.g. \"{}s\"",
+            value,
+            value / 1000.0
+        )))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        (self.parse)(value).map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Millis {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TextOrMillis {
+            expecting: "milliseconds or a duration string like \"1.5s\"",
+            parse: |text| parse_millis(text).map(Millis),
+            from_millis: Millis,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Interval {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TextOrMillis {
+            expecting: "milliseconds, a duration like \"1.5s\" or a range like \"1.2s..1.8s\"",
+            parse: parse_interval,
+            from_millis: Interval::Fixed,
+        })
+    }
+}
+
+impl JsonSchema for Millis {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        "Millis".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Milliseconds, or a duration with a unit such as \"800ms\" or \"1.5s\"",
+            "anyOf": [
+                { "type": "integer", "minimum": 0 },
+                { "type": "string", "pattern": DURATION_PATTERN },
+            ],
+        })
+    }
+}
+
+impl JsonSchema for Interval {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        "Interval".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Interval as milliseconds, a duration like \"1.5s\", or a range like \"1.2s..1.8s\" (sets both base and variance)",
+            "anyOf": [
+                { "type": "integer", "minimum": 0 },
+                { "type": "string", "pattern": INTERVAL_PATTERN },
+            ],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations_exactly() {
+        assert_eq!(parse_millis("1200"), Ok(1200));
+        assert_eq!(parse_millis(" 800ms "), Ok(800));
+        assert_eq!(parse_millis("1.5s"), Ok(1500));
+        assert_eq!(parse_millis("2.01s"), Ok(2010));
+        assert_eq!(parse_millis("0.29s"), Ok(290));
+        assert_eq!(parse_millis("1.100s"), Ok(1100));
+        assert_eq!(parse_millis("0.5m"), Ok(30_000));
+        assert_eq!(parse_millis("0.00005m"), Ok(3));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        assert!(parse_millis("1.0005s")
+            .unwrap_err()
+            .contains("whole number"));
+        assert!(parse_millis("1.5ms").unwrap_err().contains("whole number"));
+        assert!(parse_millis("-5ms").unwrap_err().contains("negative"));
+        for text in ["", "s", "1.", ".5s", "1e3", "1,5s", "abc", "1.2.3s"] {
+            assert!(parse_millis(text).is_err(), "{:?} should be rejected", text);
+        }
+        assert!(parse_millis("99999999999999999999s")
+            .unwrap_err()
+            .contains("too long"));
+    }
+
+    #[test]
+    fn ranges_keep_their_exact_bounds() {
+        assert_eq!(
+            parse_interval("1.2s..1.8s"),
+            Ok(Interval::Range {
+                base: 1500,
+                variance: 300
+            })
+        );
+        assert_eq!(parse_interval("2.01s"), Ok(Interval::Fixed(2010)));
+        assert_eq!(
+            parse_interval("1000..1000ms"),
+            Ok(Interval::Range {
+                base: 1000,
+                variance: 0
+            })
+        );
+        assert!(parse_interval("1000..1001ms")
+            .unwrap_err()
+            .contains("even number"));
+        assert!(parse_interval("2s..1s").unwrap_err().contains("larger"));
+    }
+}
@@ -0,0 +1,190 @@
+//! File formats a config can be written in, chosen by file extension
+//!
+//! Every format is read into the same YAML document model, so migrations and
+//! validation don't care which one a file uses.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Every format, the default first
+    pub const ALL: [ConfigFormat; 3] = [Self::Yaml, Self::Toml, Self::Json];
+
+    /// Format of a file from its extension; files without one are YAML
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+            return Ok(Self::Yaml);
+        };
+        match extension.to_ascii_lowercase().as_str() {
+            "yaml" | "yml" => Ok(Self::Yaml),
+            "toml" => Ok(Self::Toml),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported config file extension '.{}' in {} (use .yaml, .toml or .json)",
+                extension,
+                path.display()
+            )),
+        }
+    }
+
+    /// Extension used for new files in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+            Self::Json => "json",
+        }
+    }
+
+    /// Parse a document into the YAML model used by migrations
+    pub fn parse(self, content: &str) -> Result<Value> {
+        Ok(match self {
+            Self::Yaml => serde_yaml::from_str(content)?,
+            Self::Toml => toml::from_str(content)?,
+            Self::Json => serde_json::from_str(content)?,
+        })
+    }
+
+    /// Write a value in this format
+    pub fn render<T: Serialize>(self, value: &T) -> Result<String> {
+        Ok(match self {
+            Self::Yaml => serde_yaml::to_string(value)?,
+            Self::Toml => {
+                // TOML has no null, so unset optional fields are left out
+                let mut document = serde_yaml::to_value(value)?;
+                strip_nulls(&mut document);
+                toml::to_string_pretty(&document)?
+            }
+            Self::Json => serde_json::to_string_pretty(value)? + "\n",
+        })
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Yaml => "YAML",
+            Self::Toml => "TOML",
+            Self::Json => "JSON",
+        })
+    }
+}
+
+/// A document value on one line for messages; strings are shown without quotes
+pub fn one_line(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => serde_yaml::to_string(other)
+            .map(|yaml| yaml.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Mapping(mapping) => {
+            mapping.retain(|_, v| !v.is_null());
+            mapping.values_mut().for_each(strip_nulls);
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    /// Every optional field set, including ones only the main file has
+    const FULL: &str = "version: 2
+active_profile: witch
+profile_cycle_hotkey: F8
+game_config: poe2_production_Config.ini
+session:
+  max_runtime_minutes: 90
+  break_reminder_minutes: 30
+macros:
+  - id: life
+    name: Life flask
+    key: '1'
+    interval_ms: 4000
+    random_variance_ms: 250
+    toggle_hotkey: F9
+    enabled_by_default: false
+  - action_type: mouse
+    mouse_button: right
+    interval_ms: 700
+    toggle_hotkey: F10
+  - game_action: skill_slot_3
+    interval_ms: 1500
+    toggle_hotkey: F11
+";
+
+    const EXTENDS: &str = "extends: base
+version: 2
+session:
+  break_reminder_minutes: 20
+macros:
+  - name: life
+    interval: 1.2s..1.8s
+  - name: buff
+    remove: true
+";
+
+    /// YAML to TOML to JSON and back to YAML
+    fn round_trip(yaml: &str) -> String {
+        let mut content = yaml.to_string();
+        let mut from = ConfigFormat::Yaml;
+        for to in [ConfigFormat::Toml, ConfigFormat::Json, ConfigFormat::Yaml] {
+            content = Config::convert(&content, from, to)
+                .unwrap_or_else(|e| panic!("{} to {}: {}", from, to, e));
+            from = to;
+        }
+        content
+    }
+
+    #[test]
+    fn configs_survive_every_format() {
+        let converted = round_trip(FULL);
+        let (before, _) = Config::parse(FULL, ConfigFormat::Yaml).unwrap();
+        let (after, _) = Config::parse(&converted, ConfigFormat::Yaml).unwrap();
+        assert_eq!(after, before);
+        assert_eq!(after.active_profile.as_deref(), Some("witch"));
+        assert_eq!(after.macros[1].mouse_button, before.macros[1].mouse_button);
+        assert_eq!(after.macros[2].game_action.as_deref(), Some("skill_slot_3"));
+    }
+
+    #[test]
+    fn extending_profiles_survive_every_format() {
+        let converted = round_trip(EXTENDS);
+        assert_eq!(
+            ConfigFormat::Yaml.parse(&converted).unwrap(),
+            ConfigFormat::Yaml.parse(EXTENDS).unwrap()
+        );
+    }
+
+    #[test]
+    fn values_a_format_cannot_hold_are_refused() {
+        // JSON object keys are always strings
+        let numbered = "extends: base\nsession:\n  1: 2\n";
+        let error = Config::convert(numbered, ConfigFormat::Yaml, ConfigFormat::Json)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("would change the profile"), "{}", error);
+
+        // TOML has no null
+        let null_entry = "extends: base\nmacros:\n  - ~\n";
+        assert!(Config::convert(null_entry, ConfigFormat::Yaml, ConfigFormat::Toml).is_err());
+    }
+}
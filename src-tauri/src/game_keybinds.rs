@@ -0,0 +1,551 @@
+//! Path of Exile 2's own keybindings, read from the game's config file
+//!
+//! The game stores its binds in `poe2_production_Config.ini` under
+//! `[ACTION_KEYS]`, one action per line, e.g. `use_flask_in_slot1=49 0 0`.
+//! The first number is the Windows virtual-key code (0 when unbound) and the
+//! second is non-zero when the bind needs Shift, Ctrl or Alt held. Knowing
+//! them lets validation warn about toggle hotkeys the game also reacts to and
+//! macro keys that press nothing in game, and lets macros target a game
+//! action (`game_action: flask_1`) instead of a key, so shared profiles keep
+//! working for players who rebound their flasks or skills.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::config::{ActionType, Config, MouseButton};
+use crate::keys;
+use crate::validation::ValidationReport;
+
+/// File name of the game's settings, next to its other user files
+pub const GAME_CONFIG_FILE: &str = "poe2_production_Config.ini";
+
+const ACTION_KEYS_SECTION: &str = "ACTION_KEYS";
+
+/// One game action and the key it is bound to
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Keybind {
+    /// Action name as the game writes it, e.g. `use_flask_in_slot1`
+    pub action: String,
+    /// Key name in the config's spelling, e.g. `1`, `q`, `F9` or `mouse left`
+    pub key: String,
+    /// Whether Shift, Ctrl or Alt has to be held as well
+    pub modified: bool,
+}
+
+/// Every bound action in a game config file
+#[derive(Debug, Clone, Serialize)]
+pub struct GameKeybinds {
+    pub path: PathBuf,
+    pub binds: Vec<Keybind>,
+}
+
+impl GameKeybinds {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Ok(Self::parse(path, &content))
+    }
+
+    /// Read the `[ACTION_KEYS]` section, skipping unbound actions and lines it doesn't understand
+    pub fn parse(path: &Path, content: &str) -> Self {
+        let mut binds = Vec::new();
+        let mut in_section = false;
+        for line in content.lines().map(str::trim) {
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_section = section.eq_ignore_ascii_case(ACTION_KEYS_SECTION);
+                continue;
+            }
+            if !in_section {
+                continue;
+            }
+            let Some((action, value)) = line.split_once('=') else {
+                continue;
+            };
+            let mut numbers = value.split_whitespace().map(str::parse::<u32>);
+            let Some(Ok(code)) = numbers.next() else {
+                debug!("Skipping game keybind line '{}'", line);
+                continue;
+            };
+            if code == 0 {
+                continue;
+            }
+            binds.push(Keybind {
+                action: action.trim().to_string(),
+                key: key_name(code),
+                modified: matches!(numbers.next(), Some(Ok(flags)) if flags != 0),
+            });
+        }
+        Self {
+            path: path.to_path_buf(),
+            binds,
+        }
+    }
+
+    /// Actions that fire when `key` is pressed on its own
+    pub fn actions_on(&self, key: &str) -> Vec<&str> {
+        self.binds
+            .iter()
+            .filter(|bind| !bind.modified && same_key(&bind.key, key))
+            .map(|bind| bind.action.as_str())
+            .collect()
+    }
+
+    /// Bind of the game action a macro targets, e.g. `flask_1`
+    pub fn bind_of(&self, game_action: &str) -> Option<&Keybind> {
+        let action = game_config_action(game_action);
+        self.binds.iter().find(|bind| bind.action == action)
+    }
+
+    /// Warn about hotkeys the game also reacts to and keys it ignores
+    pub fn check(&self, config: &Config, report: &mut ValidationReport) {
+        let file = self.path.display();
+        for (idx, macro_config) in config.macros.iter().enumerate() {
+            let actions = self.actions_on(&macro_config.toggle_hotkey);
+            if !actions.is_empty() {
+                report.warning(
+                    format!("macros[{}].toggle_hotkey", idx),
+                    "game_bind_conflict",
+                    format!(
+                        "Macro '{}' is toggled with {}, which the game also binds to {}",
+                        macro_config.label(),
+                        macro_config.toggle_hotkey,
+                        actions.join(", ")
+                    ),
+                );
+            }
+
+            if let Some(game_action) = &macro_config.game_action {
+                if self.bind_of(game_action).is_none() {
+                    report.warning(
+                        format!("macros[{}].game_action", idx),
+                        "unbound_game_action",
+                        format!(
+                            "Macro '{}' targets {}, which isn't bound to a key in {}",
+                            macro_config.label(),
+                            game_action,
+                            file
+                        ),
+                    );
+                }
+                continue;
+            }
+
+            let (field, key) = match macro_config.action_type {
+                ActionType::Keyboard => ("key", macro_config.key.clone()),
+                ActionType::Mouse => match &macro_config.mouse_button {
+                    Some(button) => ("mouse_button", mouse_name(button).to_string()),
+                    None => continue,
+                },
+            };
+            if !key.is_empty() && self.actions_on(&key).is_empty() {
+                report.warning(
+                    format!("macros[{}].{}", idx, field),
+                    "unbound_game_key",
+                    format!(
+                        "Macro '{}' presses {}, which no game action is bound to in {}",
+                        macro_config.label(),
+                        key,
+                        file
+                    ),
+                );
+            }
+        }
+
+        if let Some(cycle_hotkey) = &config.profile_cycle_hotkey {
+            let actions = self.actions_on(cycle_hotkey);
+            if !actions.is_empty() {
+                report.warning(
+                    "profile_cycle_hotkey",
+                    "game_bind_conflict",
+                    format!(
+                        "Profile cycle hotkey {} is also bound in game to {}",
+                        cycle_hotkey,
+                        actions.join(", ")
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Where the game keeps its config: `configured` if set, else the usual
+/// `Documents/My Games/Path of Exile 2` folder
+pub fn locate(configured: Option<&str>) -> Option<PathBuf> {
+    match configured {
+        Some(path) => Some(PathBuf::from(path)),
+        None => dirs::document_dir().map(|documents| {
+            documents
+                .join("My Games")
+                .join("Path of Exile 2")
+                .join(GAME_CONFIG_FILE)
+        }),
+    }
+}
+
+/// Add warnings about the game's keybinds to a validation report
+///
+/// Without a `configured` path a missing game config is fine (the game may be
+/// installed elsewhere or not at all); a configured one that can't be read is
+/// reported.
+pub fn check(configured: Option<&str>, config: &Config, report: &mut ValidationReport) {
+    let Some(path) = locate(configured) else {
+        return;
+    };
+    match GameKeybinds::load(&path) {
+        Ok(keybinds) => keybinds.check(config, report),
+        Err(e) if configured.is_some() => {
+            report.warning("game_config", "game_config_unreadable", e.to_string())
+        }
+        Err(e) => debug!("Not checking game keybinds: {}", e),
+    }
+}
+
+/// A macro's game action and the key it was resolved to, for log messages
+#[derive(Debug, Clone)]
+pub struct ResolvedAction {
+    pub label: String,
+    pub game_action: String,
+    pub key: String,
+}
+
+impl fmt::Display for ResolvedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Macro '{}': {} is bound to {}",
+            self.label, self.game_action, self.key
+        )
+    }
+}
+
+/// `config` with every macro that targets a game action pressing the key
+/// the game has bound to it
+///
+/// Configs without game actions are returned as they are, without looking
+/// for the game config. Fails when it can't be read, or an action isn't
+/// bound to a key or mouse button macros can press.
+pub fn resolve(configured: Option<&str>, config: &Config) -> Result<(Config, Vec<ResolvedAction>)> {
+    if config.macros.iter().all(|m| m.game_action.is_none()) {
+        return Ok((config.clone(), Vec::new()));
+    }
+    let path = locate(configured).ok_or_else(|| {
+        anyhow::anyhow!("Macros target game actions, but no game config was found; set game_config")
+    })?;
+    let keybinds = GameKeybinds::load(&path)?;
+
+    let mut config = config.clone();
+    let mut resolved = Vec::new();
+    for macro_config in &mut config.macros {
+        let Some(game_action) = macro_config.game_action.clone() else {
+            continue;
+        };
+        let label = macro_config.label().to_string();
+        let bind = keybinds.bind_of(&game_action).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Macro '{}' targets {}, which isn't bound to a key in {}",
+                label,
+                game_action,
+                path.display()
+            )
+        })?;
+        if bind.modified {
+            return Err(anyhow::anyhow!(
+                "Macro '{}' targets {}, which is bound to {} with a modifier; macros only press plain keys",
+                label,
+                game_action,
+                bind.key
+            ));
+        }
+
+        if let Some(button) = mouse_button(&bind.key) {
+            macro_config.action_type = ActionType::Mouse;
+            macro_config.mouse_button = Some(button);
+        } else if keys::parse_key(&bind.key).is_some() {
+            macro_config.action_type = ActionType::Keyboard;
+            macro_config.key = bind.key.clone();
+            macro_config.mouse_button = None;
+        } else {
+            return Err(anyhow::anyhow!(
+                "Macro '{}' targets {}, which is bound to {}, a key macros can't press (supported: {})",
+                label,
+                game_action,
+                bind.key,
+                keys::SUPPORTED_KEYS.join(", ")
+            ));
+        }
+        resolved.push(ResolvedAction {
+            label,
+            game_action,
+            key: bind.key.clone(),
+        });
+    }
+    Ok((config, resolved))
+}
+
+/// Action in the game config that a macro's game action stands for
+///
+/// `flask_<n>` and `skill_slot_<n>` are short names for the flask and skill
+/// bar slots; anything else is taken as a game config action name as is.
+fn game_config_action(game_action: &str) -> String {
+    let slot = |prefix: &str| {
+        game_action
+            .strip_prefix(prefix)
+            .and_then(|n| n.parse::<u32>().ok())
+    };
+    if let Some(n) = slot("flask_") {
+        return format!("use_flask_in_slot{}", n);
+    }
+    if let Some(n) = slot("skill_slot_") {
+        return format!("use_bound_skill{}", n);
+    }
+    game_action.to_string()
+}
+
+fn same_key(bind: &str, key: &str) -> bool {
+    bind.eq_ignore_ascii_case(key)
+}
+
+fn mouse_button(key: &str) -> Option<MouseButton> {
+    [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+        .into_iter()
+        .find(|button| mouse_name(button) == key)
+}
+
+fn mouse_name(button: &MouseButton) -> &'static str {
+    match button {
+        MouseButton::Left => "mouse left",
+        MouseButton::Right => "mouse right",
+        MouseButton::Middle => "mouse middle",
+    }
+}
+
+/// Name of a Windows virtual-key code, spelled like keys in the config
+fn key_name(code: u32) -> String {
+    match code {
+        0x01 => "mouse left".to_string(),
+        0x02 => "mouse right".to_string(),
+        0x04 => "mouse middle".to_string(),
+        0x05 => "mouse 4".to_string(),
+        0x06 => "mouse 5".to_string(),
+        0x08 => "Backspace".to_string(),
+        0x09 => "Tab".to_string(),
+        0x0D => "Enter".to_string(),
+        0x10 => "Shift".to_string(),
+        0x11 => "Ctrl".to_string(),
+        0x12 => "Alt".to_string(),
+        0x1B => "Esc".to_string(),
+        0x20 => "Space".to_string(),
+        0x25 => "Left".to_string(),
+        0x26 => "Up".to_string(),
+        0x27 => "Right".to_string(),
+        0x28 => "Down".to_string(),
+        0x30..=0x39 | 0x41..=0x5A => char::from_u32(code)
+            .map(|c| c.to_ascii_lowercase().to_string())
+            .unwrap_or_default(),
+        0x60..=0x69 => format!("Numpad{}", code - 0x60),
+        0x70..=0x87 => format!("F{}", code - 0x6F),
+        0xC0 => "`".to_string(),
+        other => format!("key code {}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KeyMacro;
+    use crate::format::ConfigFormat;
+
+    const GAME_CONFIG: &str = "\
+[GENERAL]
+use_flask_in_slot3=51 0 0
+
+[ACTION_KEYS]
+use_flask_in_slot1=49 0 0
+use_flask_in_slot2=50 0 0
+use_bound_skill1=1 0 0
+use_bound_skill2=81 0 0
+use_bound_skill3=87 1 0
+use_bound_skill4=0 0 0
+weapon_swap=x 0 0
+show_map=112 0 0
+toggle_overlay=9 0 0
+
+[UI]
+chat=13 0 0
+";
+
+    fn keybinds() -> GameKeybinds {
+        GameKeybinds::parse(Path::new(GAME_CONFIG_FILE), GAME_CONFIG)
+    }
+
+    fn bind(action: &str, key: &str, modified: bool) -> Keybind {
+        Keybind {
+            action: action.to_string(),
+            key: key.to_string(),
+            modified,
+        }
+    }
+
+    #[test]
+    fn only_bound_action_keys_are_read() {
+        assert_eq!(
+            keybinds().binds,
+            vec![
+                bind("use_flask_in_slot1", "1", false),
+                bind("use_flask_in_slot2", "2", false),
+                bind("use_bound_skill1", "mouse left", false),
+                bind("use_bound_skill2", "q", false),
+                bind("use_bound_skill3", "w", true),
+                bind("show_map", "F1", false),
+                bind("toggle_overlay", "Tab", false),
+            ]
+        );
+
+        let lowercase =
+            GameKeybinds::parse(Path::new(""), "[action_keys]\nuse_flask_in_slot1=49\n");
+        assert_eq!(
+            lowercase.binds,
+            vec![bind("use_flask_in_slot1", "1", false)]
+        );
+    }
+
+    #[test]
+    fn modified_binds_only_fire_with_their_modifier() {
+        let keybinds = keybinds();
+        assert_eq!(keybinds.actions_on("Q"), vec!["use_bound_skill2"]);
+        assert!(keybinds.actions_on("w").is_empty());
+        assert_eq!(
+            keybinds.bind_of("skill_slot_3"),
+            Some(&bind("use_bound_skill3", "w", true))
+        );
+    }
+
+    #[test]
+    fn key_codes_are_named_like_config_keys() {
+        let names: Vec<String> = [0x31, 0x51, 0x02, 0x60, 0x70, 0x87, 0xC0, 0xFF]
+            .into_iter()
+            .map(key_name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "1",
+                "q",
+                "mouse right",
+                "Numpad0",
+                "F1",
+                "F24",
+                "`",
+                "key code 255"
+            ]
+        );
+    }
+
+    #[test]
+    fn slot_names_stand_for_game_config_actions() {
+        assert_eq!(game_config_action("flask_2"), "use_flask_in_slot2");
+        assert_eq!(game_config_action("skill_slot_10"), "use_bound_skill10");
+        assert_eq!(game_config_action("flask_x"), "flask_x");
+        assert_eq!(game_config_action("show_map"), "show_map");
+    }
+
+    /// Resolve a single macro targeting `game_action` against [`GAME_CONFIG`]
+    fn resolve_one(game_action: &str, extra: &str) -> Result<KeyMacro> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(GAME_CONFIG_FILE);
+        fs::write(&path, GAME_CONFIG).unwrap();
+        let (config, _) = Config::parse(
+            &format!(
+                "version: 2\nmacros:\n  - game_action: {}\n    interval_ms: 1000\n    toggle_hotkey: F9\n{}",
+                game_action, extra
+            ),
+            ConfigFormat::Yaml,
+        )
+        .unwrap();
+        let (resolved, actions) = resolve(path.to_str(), &config)?;
+        assert_eq!(actions.len(), 1);
+        Ok(resolved.macros[0].clone())
+    }
+
+    #[test]
+    fn game_actions_resolve_to_their_binds() {
+        let flask = resolve_one("flask_1", "").unwrap();
+        assert_eq!(flask.action_type, ActionType::Keyboard);
+        assert_eq!(flask.key, "1");
+
+        let skill = resolve_one("skill_slot_1", "").unwrap();
+        assert_eq!(skill.action_type, ActionType::Mouse);
+        assert_eq!(skill.mouse_button, Some(MouseButton::Left));
+    }
+
+    #[test]
+    fn keyboard_binds_clear_a_stale_mouse_button() {
+        let resolved = resolve_one(
+            "skill_slot_2",
+            "    action_type: mouse\n    mouse_button: right\n",
+        )
+        .unwrap();
+        assert_eq!(resolved.action_type, ActionType::Keyboard);
+        assert_eq!(resolved.key, "q");
+        assert_eq!(resolved.mouse_button, None);
+    }
+
+    #[test]
+    fn modified_unsupported_and_unbound_binds_fail() {
+        let error = |game_action| resolve_one(game_action, "").unwrap_err().to_string();
+        assert!(error("skill_slot_3").contains("with a modifier"));
+        assert!(error("toggle_overlay").contains("a key macros can't press"));
+        assert!(error("skill_slot_4").contains("isn't bound to a key"));
+    }
+
+    #[test]
+    fn check_warns_about_clashing_hotkeys_and_unbound_keys() {
+        let (config, _) = Config::parse(
+            "version: 2
+profile_cycle_hotkey: Tab
+macros:
+  - key: '1'
+    interval_ms: 1000
+    toggle_hotkey: F1
+  - key: e
+    interval_ms: 1000
+    toggle_hotkey: F9
+  - key: w
+    interval_ms: 1000
+    toggle_hotkey: F10
+  - action_type: mouse
+    mouse_button: left
+    interval_ms: 1000
+    toggle_hotkey: F11
+  - game_action: flask_3
+    interval_ms: 1000
+    toggle_hotkey: F12
+",
+            ConfigFormat::Yaml,
+        )
+        .unwrap();
+        let mut report = ValidationReport::new();
+        keybinds().check(&config, &mut report);
+
+        let warnings: Vec<(&str, &str)> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.path.as_str(), issue.code))
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                ("macros[0].toggle_hotkey", "game_bind_conflict"),
+                ("macros[1].key", "unbound_game_key"),
+                ("macros[2].key", "unbound_game_key"),
+                ("macros[4].game_action", "unbound_game_action"),
+                ("profile_cycle_hotkey", "game_bind_conflict"),
+            ]
+        );
+        assert!(report.issues[0].message.contains("show_map"));
+    }
+}
@@ -0,0 +1,833 @@
+//! Profiles that build on another profile or a base file with `extends:`
+//!
+//! A profile's own document is merged onto everything it extends. Top-level
+//! fields and `session` settings replace the inherited ones. Macros are
+//! matched by `name`: an entry named like an inherited macro overrides just
+//! the fields it sets, `remove: true` drops the inherited macro, and any
+//! other entry is added after the inherited macros.
+
+use anyhow::Result;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, KeyMacro};
+use crate::duration::Interval;
+use crate::format::ConfigFormat;
+use crate::migration;
+use crate::schema;
+use crate::storage;
+use crate::validation::ValidationReport;
+use crate::yaml_edit;
+
+pub const EXTENDS_KEY: &str = "extends";
+const REMOVE_KEY: &str = "remove";
+const NAME_KEY: &str = "name";
+
+/// Main config file settings that are never inherited
+const LOCAL_KEYS: &[&str] = &["active_profile", "profile_cycle_hotkey", "game_config"];
+
+/// Timing fields written as milliseconds, replaced by an inherited `interval` and vice versa
+pub const MILLIS_TIMING_KEYS: &[&str] = &["interval_ms", "random_variance_ms"];
+pub const INTERVAL_KEY: &str = "interval";
+
+/// A file taking part in a profile, for messages
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    /// Profile stored in the file, unless it is a plain base file
+    pub profile: Option<String>,
+    pub path: PathBuf,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.profile {
+            Some(profile) => write!(f, "profile '{}' ({})", profile, self.path.display()),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
+}
+
+/// A profile merged with everything it extends
+#[derive(Debug, Clone)]
+pub struct Resolved {
+    /// Merged document, ready to be read as a `Config`
+    pub document: Value,
+    /// Files that were merged, the base first and the profile itself last
+    pub layers: Vec<Origin>,
+    sources: Sources,
+}
+
+/// Which layer each merged value came from, as indexes into `Resolved::layers`
+#[derive(Debug, Clone, Default)]
+struct Sources {
+    /// Top-level and `session` fields, e.g. `session.max_runtime_minutes`
+    fields: HashMap<String, usize>,
+    /// One entry per merged macro
+    macros: Vec<MacroSources>,
+}
+
+#[derive(Debug, Clone)]
+struct MacroSources {
+    /// Layer that introduced the macro
+    entry: usize,
+    fields: HashMap<String, usize>,
+}
+
+/// Read one layer, upgrading its version in memory only
+pub fn read_layer(path: &Path) -> Result<Value> {
+    let format = ConfigFormat::from_path(path)?;
+    let mut document = format.parse(&fs::read_to_string(path)?)?;
+    // `extends` is newer than versioning, so such files are never version 1
+    if let Some(root) = document.as_mapping_mut() {
+        if root.contains_key(EXTENDS_KEY) && !root.contains_key("version") {
+            root.insert("version".into(), migration::current_version().into());
+        }
+    }
+    migration::migrate(&mut document)?;
+    Ok(document)
+}
+
+/// What a document extends, if anything
+pub fn extends_of(document: &Value) -> Result<Option<String>> {
+    match document.get(EXTENDS_KEY) {
+        None => Ok(None),
+        Some(Value::String(reference)) => Ok(Some(reference.clone())),
+        Some(_) => Err(anyhow::anyhow!(
+            "`{}` must be a profile name or a file path",
+            EXTENDS_KEY
+        )),
+    }
+}
+
+/// Merge `origin` with everything it extends
+///
+/// `locate` turns an `extends` value found in a file into the file it means.
+pub fn resolve(origin: Origin, locate: &dyn Fn(&str, &Path) -> Result<Origin>) -> Result<Resolved> {
+    // Walk up to the base, then merge back down
+    let mut chain: Vec<(Origin, Value)> = Vec::new();
+    let mut next = Some(origin);
+    while let Some(origin) = next.take() {
+        if chain
+            .iter()
+            .any(|(seen, _)| same_file(&seen.path, &origin.path))
+        {
+            let cycle: Vec<String> = chain
+                .iter()
+                .map(|(o, _)| o.to_string())
+                .chain([origin.to_string()])
+                .collect();
+            return Err(anyhow::anyhow!(
+                "Profile inheritance loops: {}",
+                cycle.join(" extends ")
+            ));
+        }
+
+        let document =
+            read_layer(&origin.path).map_err(|e| anyhow::anyhow!("{}: {}", origin, e))?;
+        if let Some(reference) =
+            extends_of(&document).map_err(|e| anyhow::anyhow!("{}: {}", origin, e))?
+        {
+            next = Some(
+                locate(&reference, &origin.path)
+                    .map_err(|e| anyhow::anyhow!("{}: {}", origin, e))?,
+            );
+        }
+        chain.push((origin, document));
+    }
+
+    let mut resolved = Resolved {
+        document: Value::Mapping(Mapping::new()),
+        layers: Vec::new(),
+        sources: Sources::default(),
+    };
+    for (origin, document) in chain.into_iter().rev() {
+        resolved.strip_local_keys();
+        resolved.merge(document, origin)?;
+    }
+    Ok(resolved)
+}
+
+impl Resolved {
+    /// Merge one more layer on top
+    fn merge(&mut self, document: Value, origin: Origin) -> Result<()> {
+        let layer = self.layers.len();
+        let Value::Mapping(root) = document else {
+            return Err(anyhow::anyhow!("{}: config must be a mapping", origin));
+        };
+        self.layers.push(origin);
+
+        for (key, value) in root {
+            let Some(name) = key.as_str().map(str::to_string) else {
+                continue;
+            };
+            match name.as_str() {
+                EXTENDS_KEY => {}
+                "macros" => self.merge_macros(value, layer)?,
+                "session" => self.merge_session(value, layer),
+                _ => {
+                    self.sources.fields.insert(name, layer);
+                    self.root_mut().insert(key, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_session(&mut self, value: Value, layer: usize) {
+        let fields = &mut self.sources.fields;
+        let root = self
+            .document
+            .as_mapping_mut()
+            .expect("merged document is a mapping");
+        match (
+            root.get_mut("session").and_then(Value::as_mapping_mut),
+            value,
+        ) {
+            (Some(inherited), Value::Mapping(session)) => {
+                for (key, value) in session {
+                    if let Some(name) = key.as_str() {
+                        fields.insert(format!("session.{}", name), layer);
+                    }
+                    inherited.insert(key, value);
+                }
+            }
+            (_, value) => {
+                fields.insert("session".to_string(), layer);
+                root.insert("session".into(), value);
+            }
+        }
+    }
+
+    fn merge_macros(&mut self, value: Value, layer: usize) -> Result<()> {
+        let origin = self.layers[layer].clone();
+        let Value::Sequence(entries) = value else {
+            return Err(anyhow::anyhow!("{}: `macros` must be a list", origin));
+        };
+        self.sources
+            .fields
+            .entry("macros".to_string())
+            .or_insert(layer);
+
+        let mut macros = match self.root_mut().get_mut("macros") {
+            Some(Value::Sequence(macros)) => std::mem::take(macros),
+            _ => Vec::new(),
+        };
+        for (idx, mut entry) in entries.into_iter().enumerate() {
+            let Some(fields) = entry.as_mapping_mut() else {
+                macros.push(entry);
+                self.sources.macros.push(MacroSources {
+                    entry: layer,
+                    fields: HashMap::new(),
+                });
+                continue;
+            };
+
+            let remove = match fields.remove(REMOVE_KEY) {
+                None => false,
+                Some(Value::Bool(remove)) => remove,
+                Some(_) => {
+                    return Err(anyhow::anyhow!(
+                        "{}: macros[{}].{} must be true or false",
+                        origin,
+                        idx,
+                        REMOVE_KEY
+                    ))
+                }
+            };
+            let name = fields
+                .get(NAME_KEY)
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            let inherited = name
+                .as_deref()
+                .and_then(|name| macros.iter().position(|m| name_of(m) == Some(name)));
+
+            match (remove, inherited) {
+                (true, Some(position)) => {
+                    macros.remove(position);
+                    self.sources.macros.remove(position);
+                }
+                (true, None) => {
+                    return Err(anyhow::anyhow!(
+                        "{}: macros[{}] removes {}, but no inherited macro has that name",
+                        origin,
+                        idx,
+                        name.map(|n| format!("'{}'", n))
+                            .unwrap_or_else(|| "a macro without a name".to_string())
+                    ))
+                }
+                (false, Some(position)) => {
+                    let target = macros[position]
+                        .as_mapping_mut()
+                        .expect("named macros are mappings");
+                    let sources = &mut self.sources.macros[position];
+                    let fields = entry.as_mapping().expect("checked above");
+
+                    // Only one way of writing the timing may be left: a new
+                    // `interval` replaces the inherited milliseconds, and new
+                    // milliseconds replace an inherited `interval`
+                    if fields.contains_key(INTERVAL_KEY) {
+                        for key in MILLIS_TIMING_KEYS {
+                            target.remove(*key);
+                            sources.fields.remove(*key);
+                        }
+                    } else if MILLIS_TIMING_KEYS
+                        .iter()
+                        .any(|key| fields.contains_key(*key))
+                    {
+                        // An invalid `interval` is kept so reading the macro reports it
+                        let inherited = target.get(INTERVAL_KEY).cloned();
+                        if let Some(Ok(interval)) = inherited.map(serde_yaml::from_value) {
+                            let (base, variance) = match interval {
+                                Interval::Range { base, variance } => (base, variance),
+                                Interval::Fixed(ms) => (ms, 0),
+                            };
+                            target.remove(INTERVAL_KEY);
+                            target.insert(MILLIS_TIMING_KEYS[0].into(), base.into());
+                            target.insert(MILLIS_TIMING_KEYS[1].into(), variance.into());
+                        }
+                    }
+                    for (key, value) in fields {
+                        if let Some(field) = key.as_str() {
+                            sources.fields.insert(field.to_string(), layer);
+                        }
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+                (false, None) => {
+                    let fields = fields
+                        .keys()
+                        .filter_map(Value::as_str)
+                        .map(|field| (field.to_string(), layer))
+                        .collect();
+                    macros.push(entry);
+                    self.sources.macros.push(MacroSources {
+                        entry: layer,
+                        fields,
+                    });
+                }
+            }
+        }
+
+        self.root_mut()
+            .insert("macros".into(), Value::Sequence(macros));
+        Ok(())
+    }
+
+    /// Forget the settings only a profile's own file may set
+    fn strip_local_keys(&mut self) {
+        for key in LOCAL_KEYS {
+            self.root_mut().remove(*key);
+            self.sources.fields.remove(*key);
+        }
+    }
+
+    fn root_mut(&mut self) -> &mut Mapping {
+        self.document
+            .as_mapping_mut()
+            .expect("merged document is a mapping")
+    }
+
+    /// Whether the profile extends anything
+    pub fn is_layered(&self) -> bool {
+        self.layers.len() > 1
+    }
+
+    /// Read the merged document, naming the layer behind a bad macro
+    pub fn to_config(&self) -> Result<Config> {
+        let macros = self.document.get("macros").and_then(Value::as_sequence);
+        for (idx, entry) in macros.into_iter().flatten().enumerate() {
+            if let Err(e) = serde_yaml::from_value::<KeyMacro>(entry.clone()) {
+                let path = format!("macros[{}]", idx);
+                return Err(match self.source_of(&path) {
+                    Some(origin) if self.is_layered() => {
+                        anyhow::anyhow!("{} (from {}): {}", path, origin, e)
+                    }
+                    _ => anyhow::anyhow!("{}: {}", path, e),
+                });
+            }
+        }
+        let mut config: Config = serde_yaml::from_value(self.document.clone())?;
+        config.assign_ids();
+        Ok(config)
+    }
+
+    /// File a merged value came from, by its field path, e.g. `macros[2].key`
+    pub fn source_of(&self, path: &str) -> Option<&Origin> {
+        self.layer_of(path).and_then(|layer| self.layers.get(layer))
+    }
+
+    fn layer_of(&self, path: &str) -> Option<usize> {
+        if let Some(rest) = path.strip_prefix("macros[") {
+            let (idx, rest) = rest.split_once(']')?;
+            let sources = self.sources.macros.get(idx.parse::<usize>().ok()?)?;
+            let field = rest
+                .trim_start_matches('.')
+                .split(['.', '['])
+                .next()
+                .unwrap_or_default();
+            let written_as = if MILLIS_TIMING_KEYS.contains(&field) {
+                sources
+                    .fields
+                    .get(field)
+                    .or_else(|| sources.fields.get(INTERVAL_KEY))
+            } else {
+                sources.fields.get(field)
+            };
+            return Some(*written_as.unwrap_or(&sources.entry));
+        }
+
+        let mut path = path;
+        loop {
+            if let Some(&layer) = self.sources.fields.get(path) {
+                return Some(layer);
+            }
+            path = path.rsplit_once('.')?.0;
+        }
+    }
+
+    /// Note on every issue which file the offending value came from
+    pub fn annotate(&self, report: &mut ValidationReport) {
+        if !self.is_layered() {
+            return;
+        }
+        for issue in &mut report.issues {
+            issue.source = self.source_of(&issue.path).map(Origin::to_string);
+        }
+    }
+}
+
+/// The layer that turns the merged `parent` into `config`
+///
+/// Only what differs from the parent is written: changed fields of inherited
+/// macros by name, removals, added macros and changed settings. Entries of
+/// `own` (the profile's current layer) whose effect did not change are kept as
+/// written; `previous` is what the profile resolved to before.
+pub fn overrides(
+    parent: &Resolved,
+    config: &Config,
+    reference: &str,
+    own: &Value,
+    previous: Option<&Config>,
+) -> Result<Value> {
+    let mut parent = parent.clone();
+    parent.strip_local_keys();
+    // Inherited macros carry the ids merging gives them, so only ids of
+    // added macros, or ones changed on purpose, end up in the layer
+    let base = serde_yaml::to_value(parent.to_config()?)?;
+    let merged = serde_yaml::to_value(config)?;
+    let previous = previous.map(serde_yaml::to_value).transpose()?;
+
+    let mut layer = Mapping::new();
+    layer.insert(EXTENDS_KEY.into(), reference.into());
+    layer.insert("version".into(), migration::current_version().into());
+
+    for (key, value) in merged.as_mapping().into_iter().flatten() {
+        let Some(name) = key.as_str() else {
+            continue;
+        };
+        let unchanged = previous.as_ref().and_then(|p| p.get(name)) == Some(value);
+        let value = match name {
+            "version" | "macros" => continue,
+            _ if unchanged && own.get(name).is_some() => own[name].clone(),
+            _ if LOCAL_KEYS.contains(&name) => value.clone(),
+            "session" => {
+                let changed: Mapping = value
+                    .as_mapping()
+                    .into_iter()
+                    .flatten()
+                    .filter(|(k, v)| base.get("session").and_then(|s| s.get(*k)) != Some(*v))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                if changed.is_empty() {
+                    continue;
+                }
+                Value::Mapping(changed)
+            }
+            _ if base.get(name) == Some(value) => continue,
+            _ => value.clone(),
+        };
+        layer.insert(key.clone(), value);
+    }
+
+    let entries = macro_overrides(
+        sequence(&base, "macros"),
+        sequence(&merged, "macros"),
+        previous
+            .as_ref()
+            .map(|p| sequence(p, "macros"))
+            .unwrap_or_default(),
+        sequence(own, "macros"),
+    )?;
+    if !entries.is_empty() {
+        layer.insert("macros".into(), Value::Sequence(entries));
+    }
+
+    // Only trust a layer that merges back into exactly this config
+    let layer = Value::Mapping(layer);
+    let own_origin = parent.layers[parent.layers.len() - 1].clone();
+    parent.merge(layer.clone(), own_origin)?;
+    if parent.to_config().ok().as_ref() != Some(config) {
+        return Err(anyhow::anyhow!(
+            "inherited macros can only be changed by name and can't be reordered; give them a `name` or edit the file directly"
+        ));
+    }
+    Ok(layer)
+}
+
+/// `macros` entries of an overriding layer, see [`overrides`]
+fn macro_overrides(
+    base: &[Value],
+    merged: &[Value],
+    previous: &[Value],
+    own: &[Value],
+) -> Result<Vec<Value>> {
+    let by_name =
+        |list: &[Value], name: &str| list.iter().find(|m| name_of(m) == Some(name)).cloned();
+    let own_entry = |name: &str| {
+        own.iter()
+            .find(|e| name_of(e) == Some(name) && e.get(REMOVE_KEY).is_none())
+            .cloned()
+    };
+    let unchanged = |name: &str, value: &Value| by_name(previous, name).as_ref() == Some(value);
+    let mut entries = Vec::new();
+
+    // Inherited macros keep their order, so they line up with the start of `merged`
+    let mut kept = 0;
+    for inherited in base {
+        let name = name_of(inherited);
+        let lines_up = |m: &&Value| match name {
+            Some(_) => name_of(m) == name,
+            None => *m == inherited,
+        };
+        let Some(current) = merged.get(kept).filter(lines_up) else {
+            match name {
+                Some(name) if by_name(merged, name).is_none() => {
+                    let mut removal = Mapping::new();
+                    removal.insert(NAME_KEY.into(), name.into());
+                    removal.insert(REMOVE_KEY.into(), true.into());
+                    entries.push(Value::Mapping(removal));
+                    continue;
+                }
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "inherited macros can only be changed by name and can't be reordered; give them a `name` or edit the file directly"
+                    ))
+                }
+            }
+        };
+        kept += 1;
+        let Some(name) = name else {
+            continue;
+        };
+        if current == inherited {
+            continue;
+        }
+        if unchanged(name, current) {
+            if let Some(entry) = own_entry(name) {
+                entries.push(entry);
+                continue;
+            }
+        }
+        entries.push(changed_fields(inherited, current));
+    }
+
+    // Everything after the inherited macros was added by this layer
+    let mut unused: Vec<&Value> = own.iter().filter(|e| name_of(e).is_none()).collect();
+    for added in &merged[kept..] {
+        let reused = match name_of(added) {
+            Some(name) if unchanged(name, added) => own_entry(name),
+            Some(_) => None,
+            None => unused
+                .iter()
+                .position(|e| {
+                    serde_yaml::from_value::<KeyMacro>((*e).clone())
+                        .ok()
+                        .and_then(|m| serde_yaml::to_value(m).ok())
+                        .as_ref()
+                        == Some(added)
+                })
+                .map(|position| unused.remove(position).clone()),
+        };
+        entries.push(reused.unwrap_or_else(|| added.clone()));
+    }
+    Ok(entries)
+}
+
+/// `name` and the fields of `current` that differ from `inherited`
+fn changed_fields(inherited: &Value, current: &Value) -> Value {
+    let mut entry = Mapping::new();
+    entry.insert(NAME_KEY.into(), current[NAME_KEY].clone());
+    let timing_changed = MILLIS_TIMING_KEYS
+        .iter()
+        .any(|key| inherited.get(key) != current.get(key));
+    for (key, value) in current.as_mapping().into_iter().flatten() {
+        let field = key.as_str().unwrap_or_default();
+        // The timing is written as a pair, so neither half is left to an inherited `interval`
+        let changed = inherited.get(key) != Some(value)
+            || timing_changed && MILLIS_TIMING_KEYS.contains(&field);
+        if changed && field != NAME_KEY {
+            entry.insert(key.clone(), value.clone());
+        }
+    }
+    Value::Mapping(entry)
+}
+
+/// Write a profile's own layer, editing an existing YAML file in place
+pub fn write_layer(path: &Path, layer: &Value) -> Result<()> {
+    let format = ConfigFormat::from_path(path)?;
+    let existing = fs::read_to_string(path).ok();
+
+    let patched = match (format, &existing) {
+        (ConfigFormat::Yaml, Some(existing)) => format.parse(existing).ok().and_then(|old| {
+            yaml_edit::patch(existing, &old, layer, |patched| {
+                format.parse(patched).ok().as_ref() == Some(layer)
+            })
+        }),
+        _ => None,
+    };
+    let content = match patched {
+        Some(patched) => patched,
+        None => {
+            let mut content = format.render(layer)?;
+            if let Some(header) = existing.as_deref().and_then(schema::header_of) {
+                content = format!("{}\n{}", header, content);
+            }
+            content
+        }
+    };
+    storage::write_with_backup(path, &content)
+}
+
+fn name_of(entry: &Value) -> Option<&str> {
+    entry.get(NAME_KEY).and_then(Value::as_str)
+}
+
+fn sequence<'a>(document: &'a Value, key: &str) -> &'a [Value] {
+    document
+        .get(key)
+        .and_then(Value::as_sequence)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "version: 2
+profile_cycle_hotkey: F8
+session:
+  max_runtime_minutes: 60
+  break_reminder_minutes: 30
+macros:
+  - name: life
+    key: '1'
+    interval: 1.2s..1.8s
+    toggle_hotkey: F9
+  - name: mana
+    key: '2'
+    interval_ms: 3000
+    random_variance_ms: 100
+    toggle_hotkey: F10
+  - name: buff
+    key: w
+    interval_ms: 5000
+    toggle_hotkey: F11
+";
+
+    /// A directory of YAML files, each extending others by file name
+    fn files(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in files {
+            fs::write(dir.path().join(format!("{}.yaml", name)), content).unwrap();
+        }
+        dir
+    }
+
+    fn origin(dir: &Path, name: &str) -> Origin {
+        Origin {
+            profile: Some(name.to_string()),
+            path: dir.join(format!("{}.yaml", name)),
+        }
+    }
+
+    fn resolve_in(dir: &Path, name: &str) -> Result<Resolved> {
+        resolve(origin(dir, name), &|reference, from| {
+            Ok(origin(from.parent().unwrap(), reference))
+        })
+    }
+
+    #[test]
+    fn layers_merge_fields_and_macros_by_name() {
+        let dir = files(&[
+            ("base", BASE),
+            (
+                "mid",
+                "extends: base
+session:
+  break_reminder_minutes: 20
+macros:
+  - name: life
+    interval_ms: 900
+  - name: buff
+    remove: true
+",
+            ),
+            (
+                "top",
+                "extends: mid
+macros:
+  - name: mana
+    interval: 2s
+  - name: skill
+    key: q
+    interval_ms: 700
+    toggle_hotkey: F12
+",
+            ),
+        ]);
+        let resolved = resolve_in(dir.path(), "top").unwrap();
+        assert_eq!(resolved.layers.len(), 3);
+        let config = resolved.to_config().unwrap();
+
+        let timings: Vec<(&str, u64, u64)> = config
+            .macros
+            .iter()
+            .map(|m| (m.label(), m.interval_ms, m.random_variance_ms))
+            .collect();
+        // New milliseconds keep the inherited range's variance; a new interval replaces both
+        assert_eq!(
+            timings,
+            [("life", 900, 300), ("mana", 2000, 0), ("skill", 700, 0)]
+        );
+        assert_eq!(config.macros[0].key, "1");
+        assert_eq!(config.session.max_runtime_minutes, Some(60));
+        assert_eq!(config.session.break_reminder_minutes, Some(20));
+        // Settings of the main file are never inherited
+        assert_eq!(config.profile_cycle_hotkey, None);
+
+        let source = |path| resolved.source_of(path).unwrap().path.clone();
+        assert_eq!(source("macros[0].interval_ms"), dir.path().join("mid.yaml"));
+        assert_eq!(source("macros[0].key"), dir.path().join("base.yaml"));
+        assert_eq!(source("macros[1].interval_ms"), dir.path().join("top.yaml"));
+        assert_eq!(
+            source("session.max_runtime_minutes"),
+            dir.path().join("base.yaml")
+        );
+    }
+
+    #[test]
+    fn extends_loops_are_reported() {
+        let dir = files(&[("a", "extends: b\n"), ("b", "extends: a\n")]);
+        let error = resolve_in(dir.path(), "a").unwrap_err().to_string();
+        assert!(error.contains("Profile inheritance loops"), "{}", error);
+    }
+
+    #[test]
+    fn removing_an_unknown_macro_fails() {
+        let dir = files(&[
+            ("base", BASE),
+            (
+                "child",
+                "extends: base\nmacros:\n  - name: ghost\n    remove: true\n",
+            ),
+        ]);
+        let error = resolve_in(dir.path(), "child").unwrap_err().to_string();
+        assert!(
+            error.contains("removes 'ghost', but no inherited macro has that name"),
+            "{}",
+            error
+        );
+    }
+
+    /// Save `config` as the `child` profile extending `base`, like `ProfileStore::save`
+    fn save_child(dir: &Path, config: &Config) -> Result<()> {
+        let path = dir.join("child.yaml");
+        let parent = resolve_in(dir, "base")?;
+        let own = read_layer(&path)?;
+        let previous = resolve_in(dir, "child")?.to_config().ok();
+        let layer = overrides(&parent, config, "base", &own, previous.as_ref())?;
+        write_layer(&path, &layer)
+    }
+
+    const CHILD: &str = "# Faster life flask
+extends: base
+version: 2
+macros:
+  - name: life
+    interval_ms: 900 # was a range
+";
+
+    #[test]
+    fn saving_writes_back_only_the_child_layer() {
+        let dir = files(&[("base", BASE), ("child", CHILD)]);
+        let mut config = resolve_in(dir.path(), "child")
+            .unwrap()
+            .to_config()
+            .unwrap();
+
+        // Unchanged saves leave the file as it was written
+        save_child(dir.path(), &config).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("child.yaml")).unwrap(),
+            CHILD
+        );
+
+        config.macros[1].interval_ms = 2500;
+        config.macros.remove(2);
+        config.macros.push(KeyMacro {
+            name: Some("skill".to_string()),
+            id: String::new(),
+            key: "q".to_string(),
+            toggle_hotkey: "F12".to_string(),
+            ..config.macros[0].clone()
+        });
+        config.assign_ids();
+        save_child(dir.path(), &config).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("base.yaml")).unwrap(),
+            BASE
+        );
+        let child = fs::read_to_string(dir.path().join("child.yaml")).unwrap();
+        assert!(child.starts_with("# Faster life flask\n"), "{}", child);
+        assert!(
+            child.contains("interval_ms: 900 # was a range"),
+            "{}",
+            child
+        );
+        assert!(child.contains("interval_ms: 2500"), "{}", child);
+        assert!(child.contains("name: buff\n    remove: true"), "{}", child);
+        // Inherited fields that didn't change stay in the base
+        assert!(!child.contains("F10"), "{}", child);
+
+        let reread = resolve_in(dir.path(), "child")
+            .unwrap()
+            .to_config()
+            .unwrap();
+        assert_eq!(reread, config);
+    }
+
+    #[test]
+    fn reordering_inherited_macros_cannot_be_saved() {
+        let dir = files(&[("base", BASE), ("child", CHILD)]);
+        let mut config = resolve_in(dir.path(), "child")
+            .unwrap()
+            .to_config()
+            .unwrap();
+        config.macros.swap(0, 1);
+        let error = save_child(dir.path(), &config).unwrap_err().to_string();
+        assert!(error.contains("can't be reordered"), "{}", error);
+    }
+}
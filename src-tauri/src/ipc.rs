@@ -0,0 +1,162 @@
+//! Local sockets for controlling a running instance
+//!
+//! The daemon listens on a Unix domain socket, or a named pipe on Windows.
+//! Both sit behind [`Transport`] so the control protocol doesn't care which
+//! one it talks over; [`LocalSocket`] is the one for the current platform.
+
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// A listening endpoint that clients on the same machine connect to
+pub trait Transport: Sized + Send {
+    /// Server end of a connection to one client
+    type Connection: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+    /// Client end of a connection to the server
+    type Client: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// Start listening at `endpoint`, failing if another instance already does
+    fn bind(endpoint: &Path) -> io::Result<Self>;
+
+    /// Wait for the next client
+    fn accept(&mut self) -> impl Future<Output = io::Result<Self::Connection>> + Send;
+
+    /// Connect to whatever is listening at `endpoint`
+    fn connect(endpoint: &Path) -> impl Future<Output = io::Result<Self::Client>> + Send;
+}
+
+#[cfg(unix)]
+pub type LocalSocket = unix::UnixSocket;
+#[cfg(windows)]
+pub type LocalSocket = windows::NamedPipe;
+
+/// Where the daemon listens unless told otherwise
+///
+/// A socket in the user's runtime directory (or the temp directory) on Unix,
+/// `\\.\pipe\poe2-macro-buddy` on Windows.
+pub fn default_endpoint() -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(r"\\.\pipe\poe2-macro-buddy")
+    } else {
+        dirs::runtime_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("poe2-macro-buddy.sock")
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::Transport;
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use tokio::net::{UnixListener, UnixStream};
+    use tracing::debug;
+
+    /// A Unix domain socket, removed again when dropped
+    pub struct UnixSocket {
+        path: PathBuf,
+        listener: UnixListener,
+    }
+
+    impl Transport for UnixSocket {
+        type Connection = UnixStream;
+        type Client = UnixStream;
+
+        fn bind(endpoint: &Path) -> io::Result<Self> {
+            if endpoint.exists() {
+                // A socket nobody answers on is left over from a crashed instance
+                if std::os::unix::net::UnixStream::connect(endpoint).is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        "another instance is already listening there",
+                    ));
+                }
+                debug!("Removing stale socket {}", endpoint.display());
+                fs::remove_file(endpoint)?;
+            }
+            Ok(Self {
+                path: endpoint.to_path_buf(),
+                listener: UnixListener::bind(endpoint)?,
+            })
+        }
+
+        async fn accept(&mut self) -> io::Result<UnixStream> {
+            let (stream, _) = self.listener.accept().await?;
+            Ok(stream)
+        }
+
+        async fn connect(endpoint: &Path) -> io::Result<UnixStream> {
+            UnixStream::connect(endpoint).await
+        }
+    }
+
+    impl Drop for UnixSocket {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::Transport;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use tokio::net::windows::named_pipe::{
+        ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions,
+    };
+
+    /// All pipe instances are busy serving other clients
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    /// A named pipe such as `\\.\pipe\poe2-macro-buddy`
+    ///
+    /// Each client gets its own pipe instance; the next one is created as
+    /// soon as a client connects, so there is always one waiting.
+    pub struct NamedPipe {
+        name: PathBuf,
+        next: NamedPipeServer,
+    }
+
+    impl Transport for NamedPipe {
+        type Connection = NamedPipeServer;
+        type Client = NamedPipeClient;
+
+        fn bind(endpoint: &Path) -> io::Result<Self> {
+            let next = ServerOptions::new()
+                .first_pipe_instance(true)
+                .create(endpoint)
+                .map_err(|e| match e.kind() {
+                    io::ErrorKind::PermissionDenied => io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        "another instance is already listening there",
+                    ),
+                    _ => e,
+                })?;
+            Ok(Self {
+                name: endpoint.to_path_buf(),
+                next,
+            })
+        }
+
+        async fn accept(&mut self) -> io::Result<NamedPipeServer> {
+            self.next.connect().await?;
+            let waiting = ServerOptions::new().create(&self.name)?;
+            Ok(std::mem::replace(&mut self.next, waiting))
+        }
+
+        async fn connect(endpoint: &Path) -> io::Result<NamedPipeClient> {
+            loop {
+                match ClientOptions::new().open(endpoint) {
+                    Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                        tokio::time::sleep(Duration::from_millis(50)).await
+                    }
+                    result => return result,
+                }
+            }
+        }
+    }
+}
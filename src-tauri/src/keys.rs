@@ -0,0 +1,120 @@
+//! Key and hotkey name parsing shared by config validation and the macro engine
+
+use device_query::Keycode;
+use enigo::{Button, Key};
+
+use crate::config::MouseButton;
+
+/// Keys a macro can press
+pub const SUPPORTED_KEYS: &[&str] = &["1", "2", "3", "4", "5", "q", "w", "e", "r", "t"];
+
+/// Every spelling [`parse_key`] accepts: the supported keys, with letters
+/// also in uppercase
+pub fn key_spellings() -> Vec<String> {
+    SUPPORTED_KEYS
+        .iter()
+        .flat_map(|key| {
+            let upper = key.to_ascii_uppercase();
+            if upper == *key {
+                vec![key.to_string()]
+            } else {
+                vec![key.to_string(), upper]
+            }
+        })
+        .collect()
+}
+
+/// Hotkeys that can toggle a macro
+pub const SUPPORTED_HOTKEYS: &[&str] = &[
+    "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11", "F12",
+];
+
+/// Resolve a macro key name to the key enigo sends
+pub fn parse_key(key_str: &str) -> Option<Key> {
+    match key_str {
+        "1" => Some(Key::Unicode('1')),
+        "2" => Some(Key::Unicode('2')),
+        "3" => Some(Key::Unicode('3')),
+        "4" => Some(Key::Unicode('4')),
+        "5" => Some(Key::Unicode('5')),
+        "q" | "Q" => Some(Key::Unicode('q')),
+        "w" | "W" => Some(Key::Unicode('w')),
+        "e" | "E" => Some(Key::Unicode('e')),
+        "r" | "R" => Some(Key::Unicode('r')),
+        "t" | "T" => Some(Key::Unicode('t')),
+        _ => None,
+    }
+}
+
+/// Resolve a toggle hotkey name to the keycode polled by the engine
+pub fn parse_hotkey(hotkey: &str) -> Option<Keycode> {
+    match hotkey {
+        "F1" => Some(Keycode::F1),
+        "F2" => Some(Keycode::F2),
+        "F3" => Some(Keycode::F3),
+        "F4" => Some(Keycode::F4),
+        "F5" => Some(Keycode::F5),
+        "F6" => Some(Keycode::F6),
+        "F7" => Some(Keycode::F7),
+        "F8" => Some(Keycode::F8),
+        "F9" => Some(Keycode::F9),
+        "F10" => Some(Keycode::F10),
+        "F11" => Some(Keycode::F11),
+        "F12" => Some(Keycode::F12),
+        _ => None,
+    }
+}
+
+/// Resolve a configured mouse button to the button enigo clicks
+pub fn parse_mouse_button(button: &MouseButton) -> Button {
+    match button {
+        MouseButton::Left => Button::Left,
+        MouseButton::Right => Button::Right,
+        MouseButton::Middle => Button::Middle,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_spelling_parses() {
+        for key in key_spellings() {
+            assert!(parse_key(&key).is_some(), "{} should parse", key);
+        }
+        assert!(key_spellings().contains(&"Q".to_string()));
+    }
+
+    #[test]
+    fn other_keys_are_rejected() {
+        for key in ["6", "a", "A", "F1", "", " q", "qq"] {
+            assert!(parse_key(key).is_none(), "{} should not parse", key);
+            assert!(!key_spellings().contains(&key.to_string()));
+        }
+    }
+
+    #[test]
+    fn every_supported_hotkey_parses() {
+        for hotkey in SUPPORTED_HOTKEYS {
+            assert!(parse_hotkey(hotkey).is_some());
+        }
+        assert!(parse_hotkey("f9").is_none());
+    }
+
+    /// A macro can never press its own toggle hotkey, so config validation
+    /// does not need to check for that feedback loop
+    #[test]
+    fn keys_and_hotkeys_are_disjoint() {
+        for key in key_spellings() {
+            for hotkey in SUPPORTED_HOTKEYS {
+                assert!(
+                    !key.eq_ignore_ascii_case(hotkey),
+                    "{} is both a key and a hotkey",
+                    key
+                );
+            }
+            assert!(parse_hotkey(&key).is_none(), "{} parses as a hotkey", key);
+        }
+    }
+}
@@ -1,40 +1,187 @@
 use anyhow::Result;
-use device_query::{DeviceQuery, DeviceState, Keycode};
-use enigo::{Button, Enigo, Key, Keyboard, Mouse, Settings};
+use device_query::{DeviceQuery, DeviceState};
+use enigo::{Enigo, Keyboard, Mouse, Settings};
 use rand::Rng;
 use serde::Serialize;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock as StdRwLock};
 use std::time::Duration;
 use tokio::sync::RwLock;
 use tokio::time;
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 
 use crate::config::{ActionType, Config, KeyMacro, MouseButton};
+use crate::keys;
+use crate::overrides::Overrides;
+use crate::profiles::ProfileStore;
+use crate::session::{SessionStatus, SessionTimer};
+
+/// Consecutive input failures after which a single macro is stopped
+const MAX_MACRO_FAILURES: u32 = 5;
+
+/// Consecutive input failures across all macros after which every macro is stopped
+const MAX_BACKEND_FAILURES: u32 = 15;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct MacroStatus {
-    pub index: usize,
+    pub id: String,
+    pub name: Option<String>,
     pub enabled: bool,
     pub action_type: ActionType,
+    /// Key pressed, resolved from the game's keybinds for macros with a game action
     pub key: String,
+    /// Game action the key was resolved from, e.g. `flask_1`
+    pub game_action: Option<String>,
     pub mouse_button: Option<MouseButton>,
     pub toggle_hotkey: String,
+    /// Whether the macro was stopped after repeated input failures
+    pub errored: bool,
+    /// Last input error reported for this macro (or the whole backend)
+    pub last_error: Option<String>,
+}
+
+/// Consecutive failure counter that trips once a threshold is reached
+///
+/// Used per macro and once for the whole input backend. A tripped breaker
+/// keeps the macro from firing until it is reset.
+pub struct CircuitBreaker {
+    threshold: u32,
+    consecutive_failures: AtomicU32,
+    tripped: AtomicBool,
+    last_error: Mutex<Option<String>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            consecutive_failures: AtomicU32::new(0),
+            tripped: AtomicBool::new(false),
+            last_error: Mutex::new(None),
+        }
+    }
+
+    pub fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+    }
+
+    /// Record a failure, returning true if this failure tripped the breaker
+    pub fn record_failure(&self, error: &str) -> bool {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        *self.last_error.lock().unwrap() = Some(error.to_string());
+        failures >= self.threshold && !self.tripped.swap(true, Ordering::Relaxed)
+    }
+
+    /// Trip the breaker immediately (e.g. when the backend can't be created)
+    pub fn trip(&self, error: &str) {
+        *self.last_error.lock().unwrap() = Some(error.to_string());
+        self.tripped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.tripped.load(Ordering::Relaxed)
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    pub fn reset(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        self.tripped.store(false, Ordering::Relaxed);
+        *self.last_error.lock().unwrap() = None;
+    }
+}
+
+/// Macros changed by [`MacroEngineState::apply_config`], by id
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ApplySummary {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ApplySummary {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Runtime handles shared between the engine and the tasks of one macro
+#[derive(Clone)]
+struct MacroHandle {
+    /// Id of the macro, which stays the same across live edits
+    id: String,
+    /// Live config, re-read by the macro's tasks so edits apply without a restart
+    config: Arc<StdRwLock<KeyMacro>>,
+    enabled: Arc<AtomicBool>,
+    /// Cleared to stop this macro's tasks, e.g. when swapping profiles
+    running: Arc<AtomicBool>,
+    health: Arc<CircuitBreaker>,
+}
+
+impl MacroHandle {
+    fn new(macro_config: &KeyMacro) -> Self {
+        Self {
+            id: macro_config.id.clone(),
+            config: Arc::new(StdRwLock::new(macro_config.clone())),
+            enabled: Arc::new(AtomicBool::new(macro_config.enabled_by_default)),
+            running: Arc::new(AtomicBool::new(true)),
+            health: Arc::new(CircuitBreaker::new(MAX_MACRO_FAILURES)),
+        }
+    }
+
+    fn label(&self) -> String {
+        self.config.read().unwrap().label().to_string()
+    }
 }
 
 /// Macro engine state
+///
+/// Cheap to clone; clones share the same running engine.
+#[derive(Clone)]
 pub struct MacroEngineState {
     config: Arc<RwLock<Option<Config>>>,
     running: Arc<AtomicBool>,
-    macro_states: Arc<RwLock<Vec<Arc<AtomicBool>>>>,
+    /// Set while every macro holds fire without losing its enabled state
+    paused: Arc<AtomicBool>,
+    macros: Arc<RwLock<Vec<MacroHandle>>>,
+    backend_health: Arc<CircuitBreaker>,
+    session: Arc<RwLock<Option<Arc<SessionTimer>>>>,
+    profiles: ProfileStore,
+    /// `--set` and environment overrides applied to every config the engine runs
+    overrides: Overrides,
+    /// Log key presses and clicks instead of sending them (`--dry-run`)
+    dry_run: bool,
 }
 
 impl MacroEngineState {
-    pub fn new() -> Self {
+    pub fn new(profiles: ProfileStore, overrides: Overrides) -> Self {
         Self {
             config: Arc::new(RwLock::new(None)),
             running: Arc::new(AtomicBool::new(false)),
-            macro_states: Arc::new(RwLock::new(Vec::new())),
+            paused: Arc::new(AtomicBool::new(false)),
+            macros: Arc::new(RwLock::new(Vec::new())),
+            backend_health: Arc::new(CircuitBreaker::new(MAX_BACKEND_FAILURES)),
+            session: Arc::new(RwLock::new(None)),
+            profiles,
+            overrides,
+            dry_run: false,
+        }
+    }
+
+    pub fn profiles(&self) -> &ProfileStore {
+        &self.profiles
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Wait until the engine is stopped, e.g. by the session limit
+    pub async fn stopped(&self) {
+        while self.is_running() {
+            time::sleep(Duration::from_millis(500)).await;
         }
     }
 
@@ -43,66 +190,260 @@ impl MacroEngineState {
             return Err(anyhow::anyhow!("Macro engine is already running"));
         }
 
-        config.validate()?;
+        let config = self.runtime_config(&config, true)?;
+        let cycle_hotkey = self.profiles.cycle_hotkey()?;
+        self.check_config(&config, cycle_hotkey.as_deref())?;
 
         info!(
             "Starting POE2 Macro Engine with {} macro(s)...",
             config.macros.len()
         );
 
-        // Initialize macro states
-        let states: Vec<Arc<AtomicBool>> = config
-            .macros
-            .iter()
-            .map(|m| Arc::new(AtomicBool::new(m.enabled_by_default)))
-            .collect();
-
-        *self.macro_states.write().await = states.clone();
-        *self.config.write().await = Some(config.clone());
+        self.backend_health.reset();
+        self.paused.store(false, Ordering::Relaxed);
         self.running.store(true, Ordering::Relaxed);
+        self.spawn_macros(config.clone()).await;
 
-        // Spawn tasks for each macro
-        for (idx, macro_config) in config.macros.iter().enumerate() {
-            let macro_config = macro_config.clone();
-            let enabled = states[idx].clone();
-            let running = self.running.clone();
-
+        // Enforce session limits; the engine tears itself down when the limit hits
+        let session = Arc::new(SessionTimer::start(config.session.clone()));
+        *self.session.write().await = Some(session.clone());
+        {
+            let engine = self.clone();
+            let session = session.clone();
             tokio::spawn(async move {
-                if let Err(e) = Self::run_single_macro(idx, macro_config, enabled, running).await {
-                    warn!("Macro {} error: {}", idx, e);
+                if session.supervise(&engine.running).await {
+                    engine.clear().await;
                 }
             });
         }
 
+        if let Some(hotkey) = cycle_hotkey {
+            self.spawn_profile_cycler(hotkey, session);
+        }
+
         Ok(())
     }
 
     pub async fn stop(&self) {
         self.running.store(false, Ordering::Relaxed);
+        if let Some(session) = self.session.read().await.as_ref() {
+            session.finish();
+        }
+        self.clear().await;
+    }
+
+    /// Stop every macro task and forget the running config
+    async fn clear(&self) {
+        for handle in self.macros.write().await.drain(..) {
+            handle.running.store(false, Ordering::Relaxed);
+        }
         *self.config.write().await = None;
-        *self.macro_states.write().await = Vec::new();
         info!("Macro engine stopped");
     }
 
+    /// Replace the macros of a running engine, e.g. after a profile switch
+    ///
+    /// The old macro tasks are stopped before the new ones start; the session
+    /// timer keeps running across the swap.
+    pub async fn switch_config(&self, config: Config) -> Result<()> {
+        if !self.running.load(Ordering::Relaxed) {
+            return Err(anyhow::anyhow!("Macro engine is not running"));
+        }
+
+        let config = self.runtime_config(&config, true)?;
+        let cycle_hotkey = self.profiles.cycle_hotkey()?;
+        self.check_config(&config, cycle_hotkey.as_deref())?;
+
+        for handle in self.macros.write().await.drain(..) {
+            handle.running.store(false, Ordering::Relaxed);
+        }
+        info!("Swapping in {} macro(s)...", config.macros.len());
+        self.spawn_macros(config).await;
+        Ok(())
+    }
+
+    /// Spawn the tasks for every macro in `config` and make it the running config
+    async fn spawn_macros(&self, config: Config) {
+        let handles: Vec<MacroHandle> = config.macros.iter().map(MacroHandle::new).collect();
+
+        for handle in &handles {
+            self.spawn_macro(handle.clone());
+        }
+
+        *self.macros.write().await = handles;
+        *self.config.write().await = Some(config);
+    }
+
+    fn spawn_macro(&self, handle: MacroHandle) {
+        let backend_health = self.backend_health.clone();
+        let paused = self.paused.clone();
+        let macros = self.macros.clone();
+        let dry_run = self.dry_run;
+        tokio::spawn(Self::run_single_macro(
+            handle,
+            backend_health,
+            paused,
+            macros,
+            dry_run,
+        ));
+    }
+
+    /// Position of the macro with the given id
+    fn find(macros: &[MacroHandle], id: &str) -> Result<usize> {
+        macros
+            .iter()
+            .position(|h| h.id == id)
+            .ok_or_else(|| anyhow::anyhow!("No macro with id '{}'", id))
+    }
+
+    /// Apply a changed config to the running engine without restarting it
+    ///
+    /// Macros are matched by id: changed macros are retuned in place and keep
+    /// their enabled state, new macros are started and missing ones stopped,
+    /// wherever they moved in the list. Session limits only change on the next
+    /// start.
+    pub async fn apply_config(&self, config: Config) -> Result<ApplySummary> {
+        if !self.running.load(Ordering::Relaxed) {
+            return Err(anyhow::anyhow!("Macro engine is not running"));
+        }
+        let config = self.runtime_config(&config, false)?;
+        self.check_live_config(&config)?;
+
+        let mut summary = ApplySummary::default();
+        let mut macros = self.macros.write().await;
+        let mut previous = std::mem::take(&mut *macros);
+
+        for macro_config in &config.macros {
+            match Self::find(&previous, &macro_config.id) {
+                Ok(position) => {
+                    let handle = previous.remove(position);
+                    {
+                        let mut live = handle.config.write().unwrap();
+                        if *live != *macro_config {
+                            *live = macro_config.clone();
+                            info!(
+                                "Macro '{}' updated: {}",
+                                macro_config.label(),
+                                Self::describe(macro_config)
+                            );
+                            summary.updated.push(macro_config.id.clone());
+                        }
+                    }
+                    macros.push(handle);
+                }
+                Err(_) => {
+                    let handle = MacroHandle::new(macro_config);
+                    self.spawn_macro(handle.clone());
+                    macros.push(handle);
+                    summary.added.push(macro_config.id.clone());
+                }
+            }
+        }
+
+        for handle in previous {
+            handle.running.store(false, Ordering::Relaxed);
+            info!("Macro '{}' removed", handle.label());
+            summary.removed.push(handle.id);
+        }
+
+        *self.config.write().await = Some(config);
+        Ok(summary)
+    }
+
+    /// Watch the profile cycle hotkey and switch to the next profile when pressed
+    ///
+    /// The watcher ends with `session`, so a quick stop and start doesn't
+    /// leave the previous run's watcher cycling too.
+    fn spawn_profile_cycler(&self, hotkey: String, session: Arc<SessionTimer>) {
+        let Some(keycode) = keys::parse_hotkey(&hotkey) else {
+            return;
+        };
+        info!("Press {} to cycle profiles", hotkey);
+
+        let engine = self.clone();
+        tokio::spawn(async move {
+            let device_state = DeviceState::new();
+            let mut last_pressed = false;
+            let mut check_interval = time::interval(Duration::from_millis(100));
+
+            while engine.running.load(Ordering::Relaxed) && !session.is_finished() {
+                check_interval.tick().await;
+
+                let pressed = device_state.get_keys().contains(&keycode);
+                if pressed && !last_pressed {
+                    if let Err(e) = engine.cycle_profile().await {
+                        warn!("Failed to switch profile: {}", e);
+                    }
+                    time::sleep(Duration::from_millis(300)).await;
+                }
+                last_pressed = pressed;
+            }
+        });
+    }
+
+    /// Switch the running engine to the next profile
+    pub async fn cycle_profile(&self) -> Result<String> {
+        let next = self.profiles.next()?;
+        self.switch_profile(&next).await?;
+        Ok(next)
+    }
+
+    /// Make a profile active, swapping its macros in if the engine is running
+    pub async fn switch_profile(&self, name: &str) -> Result<()> {
+        let config = self.load_profile(name)?;
+        if self.running.load(Ordering::Relaxed) {
+            self.switch_config(config).await?;
+        }
+        if self.dry_run {
+            self.profiles.select(name);
+        } else {
+            self.profiles.set_active(name)?;
+        }
+        Ok(())
+    }
+
+    /// Re-read the active profile from disk and apply it to the running engine
+    pub async fn reload(&self) -> Result<ApplySummary> {
+        let config = self.load_profile(&self.profiles.active())?;
+        self.apply_config(config).await
+    }
+
+    /// Load a profile to run it, only reading it under `--dry-run`
+    fn load_profile(&self, name: &str) -> Result<Config> {
+        if self.dry_run {
+            self.profiles.load_read_only(name)
+        } else {
+            self.profiles.load(name)
+        }
+    }
+
     pub async fn get_status(&self) -> Vec<MacroStatus> {
         let config_guard = self.config.read().await;
-        let states_guard = self.macro_states.read().await;
+        let macros_guard = self.macros.read().await;
+        let backend_tripped = self.backend_health.is_tripped();
 
         if let Some(config) = config_guard.as_ref() {
             config
                 .macros
                 .iter()
-                .enumerate()
-                .map(|(idx, macro_config)| MacroStatus {
-                    index: idx,
-                    enabled: states_guard
-                        .get(idx)
-                        .map(|s| s.load(Ordering::Relaxed))
-                        .unwrap_or(false),
-                    action_type: macro_config.action_type.clone(),
-                    key: macro_config.key.clone(),
-                    mouse_button: macro_config.mouse_button.clone(),
-                    toggle_hotkey: macro_config.toggle_hotkey.clone(),
+                .map(|macro_config| {
+                    let handle = macros_guard.iter().find(|h| h.id == macro_config.id);
+                    MacroStatus {
+                        id: macro_config.id.clone(),
+                        name: macro_config.name.clone(),
+                        enabled: handle
+                            .map(|h| h.enabled.load(Ordering::Relaxed))
+                            .unwrap_or(false),
+                        action_type: macro_config.action_type.clone(),
+                        key: macro_config.key.clone(),
+                        game_action: macro_config.game_action.clone(),
+                        mouse_button: macro_config.mouse_button.clone(),
+                        toggle_hotkey: macro_config.toggle_hotkey.clone(),
+                        errored: backend_tripped || handle.is_some_and(|h| h.health.is_tripped()),
+                        last_error: handle
+                            .and_then(|h| h.health.last_error())
+                            .or_else(|| self.backend_health.last_error()),
+                    }
                 })
                 .collect()
         } else {
@@ -110,81 +451,228 @@ impl MacroEngineState {
         }
     }
 
-    pub async fn toggle_macro(&self, index: usize) -> Result<()> {
-        let states = self.macro_states.read().await;
-        if let Some(state) = states.get(index) {
-            let current = state.load(Ordering::Relaxed);
-            state.store(!current, Ordering::Relaxed);
-            info!("Macro #{} toggled to {}", index, !current);
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Invalid macro index: {}", index))
+    /// Status of the current session, or of the last one once the engine stopped
+    pub async fn get_session_status(&self) -> SessionStatus {
+        let running = self.running.load(Ordering::Relaxed);
+        self.session
+            .read()
+            .await
+            .as_ref()
+            .map(|session| session.status(running))
+            .unwrap_or_default()
+    }
+
+    /// Replace one macro of the running engine, effective from its next fire
+    ///
+    /// The macro keeps its id and enabled state. Returns the new running config.
+    pub async fn update_macro(&self, id: &str, mut macro_config: KeyMacro) -> Result<Config> {
+        // Hold the write lock so concurrent edits apply one at a time
+        let macros = self.macros.write().await;
+        let mut config = self.running_config().await?;
+        let handle = &macros[Self::find(&macros, id)?];
+        let position = config
+            .macros
+            .iter()
+            .position(|m| m.id == id)
+            .ok_or_else(|| anyhow::anyhow!("No macro with id '{}'", id))?;
+
+        macro_config.id = id.to_string();
+        config.macros[position] = macro_config;
+        let config = self.runtime_config(&config, false)?;
+        self.check_live_config(&config)?;
+        let macro_config = config.macros[position].clone();
+
+        info!(
+            "Macro '{}' updated: {}",
+            macro_config.label(),
+            Self::describe(&macro_config)
+        );
+        *handle.config.write().unwrap() = macro_config;
+        *self.config.write().await = Some(config.clone());
+        Ok(config)
+    }
+
+    /// Start an extra macro on the running engine, deriving an id if it has none.
+    /// Returns the new running config.
+    pub async fn add_macro(&self, macro_config: KeyMacro) -> Result<Config> {
+        let mut macros = self.macros.write().await;
+        let mut config = self.running_config().await?;
+
+        config.macros.push(macro_config);
+        config.assign_ids();
+        let config = self.runtime_config(&config, false)?;
+        self.check_live_config(&config)?;
+
+        let macro_config = &config.macros[config.macros.len() - 1];
+        let handle = MacroHandle::new(macro_config);
+        self.spawn_macro(handle.clone());
+        macros.push(handle);
+        info!(
+            "Macro '{}' added: {}",
+            macro_config.label(),
+            Self::describe(macro_config)
+        );
+        *self.config.write().await = Some(config.clone());
+        Ok(config)
+    }
+
+    /// Stop and remove one macro of the running engine. Returns the new running config.
+    pub async fn remove_macro(&self, id: &str) -> Result<Config> {
+        let mut macros = self.macros.write().await;
+        let mut config = self.running_config().await?;
+        let position = Self::find(&macros, id)?;
+
+        config.macros.retain(|m| m.id != id);
+        self.check_live_config(&config)?;
+
+        let handle = macros.remove(position);
+        handle.running.store(false, Ordering::Relaxed);
+        info!("Macro '{}' removed", handle.label());
+        *self.config.write().await = Some(config.clone());
+        Ok(config)
+    }
+
+    /// Config of the running engine, or an error when it is stopped
+    async fn running_config(&self) -> Result<Config> {
+        if !self.running.load(Ordering::Relaxed) {
+            return Err(anyhow::anyhow!("Macro engine is not running"));
         }
+        self.config
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Macro engine is not running"))
     }
 
-    async fn run_single_macro(
-        idx: usize,
-        macro_config: KeyMacro,
-        enabled: Arc<AtomicBool>,
-        running: Arc<AtomicBool>,
-    ) -> Result<()> {
-        // Log macro configuration
-        match macro_config.action_type {
-            ActionType::Keyboard => {
-                info!(
-                    "Macro #{}: Type=Keyboard, Key='{}', Interval={}ms±{}ms, Toggle='{}'",
-                    idx,
-                    macro_config.key,
-                    macro_config.interval_ms,
-                    macro_config.random_variance_ms,
-                    macro_config.toggle_hotkey
-                );
+    /// `config` as the engine runs it: with the `--set` and environment
+    /// overrides applied and game actions resolved to the keys bound to them
+    ///
+    /// Both are logged when a config is first run; live edits reapply them
+    /// quietly so they stay in effect.
+    fn runtime_config(&self, config: &Config, log: bool) -> Result<Config> {
+        let (config, applied) = self.overrides.apply(config)?;
+        let (config, resolved) = self.profiles.resolve_game_actions(&config)?;
+        if log {
+            for change in &applied {
+                info!("Override: {}", change);
             }
-            ActionType::Mouse => {
-                let button_name = match macro_config.mouse_button {
-                    Some(MouseButton::Left) => "Left",
-                    Some(MouseButton::Right) => "Right",
-                    Some(MouseButton::Middle) => "Middle",
-                    None => "Unknown",
-                };
-                info!(
-                    "Macro #{}: Type=Mouse, Button='{}', Interval={}ms±{}ms, Toggle='{}'",
-                    idx,
-                    button_name,
-                    macro_config.interval_ms,
-                    macro_config.random_variance_ms,
-                    macro_config.toggle_hotkey
-                );
+            for action in &resolved {
+                info!("{}", action);
             }
         }
+        Ok(config)
+    }
 
-        // Parse key or mouse button based on action type
-        let target_key = if macro_config.action_type == ActionType::Keyboard {
-            Some(
-                Self::str_to_key(&macro_config.key)
-                    .ok_or_else(|| anyhow::anyhow!("Invalid key: {}", macro_config.key))?,
-            )
-        } else {
-            None
-        };
+    fn check_live_config(&self, config: &Config) -> Result<()> {
+        let cycle_hotkey = self.profiles.cycle_hotkey()?;
+        self.check_config(config, cycle_hotkey.as_deref())
+    }
 
-        let target_mouse_button = if macro_config.action_type == ActionType::Mouse {
-            macro_config.mouse_button.as_ref().map(|btn| match btn {
-                MouseButton::Left => Button::Left,
-                MouseButton::Right => Button::Right,
-                MouseButton::Middle => Button::Middle,
-            })
-        } else {
-            None
+    pub async fn toggle_macro(&self, id: &str) -> Result<()> {
+        let macros = self.macros.read().await;
+        let handle = &macros[Self::find(&macros, id)?];
+
+        if handle.health.is_tripped() || self.backend_health.is_tripped() {
+            return Err(anyhow::anyhow!(
+                "Macro '{}' is stopped after repeated input failures; reset it first",
+                handle.label()
+            ));
+        }
+
+        let current = handle.enabled.load(Ordering::Relaxed);
+        handle.enabled.store(!current, Ordering::Relaxed);
+        info!(
+            "Macro '{}' {}",
+            handle.label(),
+            if current { "DISABLED" } else { "ENABLED" }
+        );
+        Ok(())
+    }
+
+    /// Enable or disable one macro, or every macro when `id` is `None`
+    ///
+    /// Macros stopped after repeated input failures are skipped when enabling
+    /// all of them, and refused when named.
+    pub async fn set_enabled(&self, id: Option<&str>, enabled: bool) -> Result<()> {
+        let macros = self.macros.read().await;
+        let handles = match id {
+            Some(id) => {
+                let handle = &macros[Self::find(&macros, id)?];
+                if enabled && (handle.health.is_tripped() || self.backend_health.is_tripped()) {
+                    return Err(anyhow::anyhow!(
+                        "Macro '{}' is stopped after repeated input failures; reset it first",
+                        handle.label()
+                    ));
+                }
+                std::slice::from_ref(handle)
+            }
+            None => &macros[..],
         };
 
-        // Parse toggle hotkey
-        let toggle_keycode = Self::str_to_keycode(&macro_config.toggle_hotkey);
+        for handle in handles {
+            if enabled && handle.health.is_tripped() {
+                continue;
+            }
+            handle.enabled.store(enabled, Ordering::Relaxed);
+            info!(
+                "Macro '{}' {}",
+                handle.label(),
+                if enabled { "ENABLED" } else { "DISABLED" }
+            );
+        }
+        Ok(())
+    }
+
+    /// Hold fire on every macro, or resume, keeping which ones are enabled
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+        info!("Macro engine {}", if paused { "PAUSED" } else { "RESUMED" });
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Clear the failure state of one macro, or of every macro when `id` is `None`
+    ///
+    /// The backend breaker is always cleared too, since no macro can fire while it
+    /// is tripped. Reset macros stay disabled until toggled on again.
+    pub async fn reset_errors(&self, id: Option<&str>) -> Result<()> {
+        let macros = self.macros.read().await;
+        match id {
+            Some(id) => macros[Self::find(&macros, id)?].health.reset(),
+            None => macros.iter().for_each(|h| h.health.reset()),
+        }
+        self.backend_health.reset();
+        info!("Macro failure state reset");
+        Ok(())
+    }
+
+    async fn run_single_macro(
+        handle: MacroHandle,
+        backend_health: Arc<CircuitBreaker>,
+        paused: Arc<AtomicBool>,
+        macros: Arc<RwLock<Vec<MacroHandle>>>,
+        dry_run: bool,
+    ) {
+        let MacroHandle {
+            id: _,
+            config,
+            enabled,
+            running,
+            health: macro_health,
+        } = handle;
 
-        // Spawn hotkey detection task
+        // Log macro configuration
+        let initial = config.read().unwrap().clone();
+        info!("Macro '{}': {}", initial.label(), Self::describe(&initial));
+
+        // Spawn hotkey detection task; the hotkey is re-read so live edits apply
         let enabled_clone = enabled.clone();
         let running_clone = running.clone();
-        let toggle_hotkey = macro_config.toggle_hotkey.clone();
+        let toggle_config = config.clone();
+        let toggle_macro_health = macro_health.clone();
+        let toggle_backend_health = backend_health.clone();
         let device_state = DeviceState::new();
 
         tokio::spawn(async move {
@@ -195,24 +683,43 @@ impl MacroEngineState {
             while running_clone.load(Ordering::Relaxed) {
                 check_interval.tick().await;
 
-                if let Some(toggle_key) = toggle_keycode {
+                let (label, toggle_hotkey) = {
+                    let live = toggle_config.read().unwrap();
+                    (live.label().to_string(), live.toggle_hotkey.clone())
+                };
+                if let Some(toggle_key) = keys::parse_hotkey(&toggle_hotkey) {
                     let keys = device_state.get_keys();
                     let toggle_pressed = keys.contains(&toggle_key);
 
                     if toggle_pressed && !last_toggle_state {
-                        let current = enabled_clone.load(Ordering::Relaxed);
-                        enabled_clone.store(!current, Ordering::Relaxed);
-
-                        if !current {
-                            info!(
-                                "Macro #{} ENABLED - Press {} to disable",
-                                idx, toggle_hotkey
+                        if toggle_backend_health.is_tripped() {
+                            // Only resetting every macro's errors clears the backend breaker
+                            warn!(
+                                "Macro '{}' not toggled: the input backend stopped every macro; reset errors to retry",
+                                label
                             );
                         } else {
-                            info!(
-                                "Macro #{} DISABLED - Press {} to enable",
-                                idx, toggle_hotkey
-                            );
+                            // Pressing the hotkey on a stopped macro retries it
+                            if toggle_macro_health.is_tripped() {
+                                toggle_macro_health.reset();
+                                enabled_clone.store(false, Ordering::Relaxed);
+                                info!("Macro '{}' failure state reset - retrying", label);
+                            }
+
+                            let current = enabled_clone.load(Ordering::Relaxed);
+                            enabled_clone.store(!current, Ordering::Relaxed);
+
+                            if !current {
+                                info!(
+                                    "Macro '{}' ENABLED - Press {} to disable",
+                                    label, toggle_hotkey
+                                );
+                            } else {
+                                info!(
+                                    "Macro '{}' DISABLED - Press {} to enable",
+                                    label, toggle_hotkey
+                                );
+                            }
                         }
 
                         time::sleep(Duration::from_millis(300)).await;
@@ -222,101 +729,211 @@ impl MacroEngineState {
             }
         });
 
-        // Main action loop (keyboard or mouse)
-        let mut enigo = Enigo::new(&Settings::default())?;
-
-        // Pre-compute button name for logging (optimization)
-        let button_name = match macro_config.mouse_button {
-            Some(MouseButton::Left) => "Left",
-            Some(MouseButton::Right) => "Right",
-            Some(MouseButton::Middle) => "Middle",
-            None => "Unknown",
-        };
+        // Main action loop (keyboard or mouse); the input backend is created on
+        // the first fire, and again after a reset if that failed. A dry run needs none
+        let mut enigo: Option<Enigo> = None;
 
         while running.load(Ordering::Relaxed) {
+            // Read the live config every cycle so edits apply from the next fire
+            let macro_config = config.read().unwrap().clone();
             let interval =
                 Self::calculate_interval(macro_config.interval_ms, macro_config.random_variance_ms);
 
             time::sleep(interval).await;
 
-            if enabled.load(Ordering::Relaxed) {
-                match macro_config.action_type {
-                    ActionType::Keyboard => {
-                        if let Some(key) = target_key {
-                            match enigo.key(key, enigo::Direction::Click) {
-                                Ok(_) => {
-                                    tracing::debug!(
-                                        "Macro #{}: Pressed key '{}' (next in ~{}ms)",
-                                        idx,
-                                        macro_config.key,
-                                        interval.as_millis()
-                                    );
-                                }
-                                Err(e) => {
-                                    warn!("Macro #{}: Failed to press key: {}", idx, e);
-                                }
-                            }
-                        }
+            // Don't fire once stopped while sleeping (e.g. swapped out by a profile switch)
+            if !running.load(Ordering::Relaxed) {
+                break;
+            }
+
+            if macro_health.is_tripped() || backend_health.is_tripped() {
+                continue;
+            }
+
+            if !enabled.load(Ordering::Relaxed) || paused.load(Ordering::Relaxed) {
+                continue;
+            }
+            if enigo.is_none() && !dry_run {
+                match Enigo::new(&Settings::default()) {
+                    Ok(created) => enigo = Some(created),
+                    Err(e) => {
+                        let message = format!("Failed to initialize input backend: {}", e);
+                        error!("Macro '{}' stopped: {}", macro_config.label(), message);
+                        macro_health.trip(&message);
+                        backend_health.trip(&message);
+                        Self::disable_all(&macros).await;
+                        continue;
                     }
-                    ActionType::Mouse => {
-                        if let Some(button) = target_mouse_button {
-                            match enigo.button(button, enigo::Direction::Click) {
-                                Ok(_) => {
-                                    tracing::debug!(
-                                        "Macro #{}: Clicked mouse button '{}' (next in ~{}ms)",
-                                        idx,
-                                        button_name,
-                                        interval.as_millis()
-                                    );
-                                }
-                                Err(e) => {
-                                    warn!("Macro #{}: Failed to click mouse button: {}", idx, e);
-                                }
-                            }
-                        }
+                }
+            }
+            let Some(enigo) = enigo.as_mut() else {
+                info!(
+                    "Macro '{}': Would {} (next in ~{}ms)",
+                    macro_config.label(),
+                    match macro_config.action_type {
+                        ActionType::Keyboard => format!("press key '{}'", macro_config.key),
+                        ActionType::Mouse => format!(
+                            "click mouse button '{}'",
+                            Self::button_name(&macro_config.mouse_button)
+                        ),
+                    },
+                    interval.as_millis()
+                );
+                continue;
+            };
+
+            let result = match macro_config.action_type {
+                ActionType::Keyboard => keys::parse_key(&macro_config.key).map(|key| {
+                    enigo.key(key, enigo::Direction::Click).map(|_| {
+                        tracing::debug!(
+                            "Macro '{}': Pressed key '{}' (next in ~{}ms)",
+                            macro_config.label(),
+                            macro_config.key,
+                            interval.as_millis()
+                        );
+                    })
+                }),
+                ActionType::Mouse => macro_config.mouse_button.as_ref().map(|button| {
+                    enigo
+                        .button(keys::parse_mouse_button(button), enigo::Direction::Click)
+                        .map(|_| {
+                            tracing::debug!(
+                                "Macro '{}': Clicked mouse button '{}' (next in ~{}ms)",
+                                macro_config.label(),
+                                Self::button_name(&macro_config.mouse_button),
+                                interval.as_millis()
+                            );
+                        })
+                }),
+            };
+
+            match result {
+                Some(Ok(())) => {
+                    macro_health.record_success();
+                    backend_health.record_success();
+                }
+                Some(Err(e)) => {
+                    let backend_tripped = Self::record_input_failure(
+                        macro_config.label(),
+                        &e.to_string(),
+                        &enabled,
+                        &macro_health,
+                        &backend_health,
+                    );
+                    if backend_tripped {
+                        Self::disable_all(&macros).await;
                     }
                 }
+                None => {}
             }
         }
+    }
+
+    /// One-line summary of a macro for log messages
+    pub(crate) fn describe(macro_config: &KeyMacro) -> String {
+        match (&macro_config.action_type, &macro_config.game_action) {
+            (ActionType::Keyboard, Some(game_action)) if macro_config.key.is_empty() => format!(
+                "Type=Keyboard, GameAction='{}', Interval={}ms±{}ms, Toggle='{}'",
+                game_action,
+                macro_config.interval_ms,
+                macro_config.random_variance_ms,
+                macro_config.toggle_hotkey
+            ),
+            (ActionType::Keyboard, _) => format!(
+                "Type=Keyboard, Key='{}', Interval={}ms±{}ms, Toggle='{}'",
+                macro_config.key,
+                macro_config.interval_ms,
+                macro_config.random_variance_ms,
+                macro_config.toggle_hotkey
+            ),
+            (ActionType::Mouse, _) => format!(
+                "Type=Mouse, Button='{}', Interval={}ms±{}ms, Toggle='{}'",
+                Self::button_name(&macro_config.mouse_button),
+                macro_config.interval_ms,
+                macro_config.random_variance_ms,
+                macro_config.toggle_hotkey
+            ),
+        }
+    }
+
+    pub(crate) fn button_name(button: &Option<MouseButton>) -> &'static str {
+        match button {
+            Some(MouseButton::Left) => "Left",
+            Some(MouseButton::Right) => "Right",
+            Some(MouseButton::Middle) => "Middle",
+            None => "Unknown",
+        }
+    }
+
+    /// Validate a config before it is run, logging any warnings
+    ///
+    /// `cycle_hotkey` is the profile cycle hotkey from the main config, which
+    /// every profile's macros must stay clear of.
+    fn check_config(&self, config: &Config, cycle_hotkey: Option<&str>) -> Result<()> {
+        let mut report = config.validate_report();
+        self.profiles.check_game_keybinds(config, &mut report);
+        self.profiles.annotate(config, &mut report);
+        for issue in report.warnings() {
+            warn!("Config warning: {}", issue);
+        }
+        report.into_result()?;
 
+        if let Some(macro_config) = config
+            .macros
+            .iter()
+            .find(|m| Some(m.toggle_hotkey.as_str()) == cycle_hotkey)
+        {
+            return Err(anyhow::anyhow!(
+                "Macro '{}' uses the profile cycle hotkey {} as its toggle hotkey",
+                macro_config.label(),
+                macro_config.toggle_hotkey
+            ));
+        }
         Ok(())
     }
 
-    fn str_to_keycode(hotkey: &str) -> Option<Keycode> {
-        match hotkey {
-            "F1" => Some(Keycode::F1),
-            "F2" => Some(Keycode::F2),
-            "F3" => Some(Keycode::F3),
-            "F4" => Some(Keycode::F4),
-            "F5" => Some(Keycode::F5),
-            "F6" => Some(Keycode::F6),
-            "F7" => Some(Keycode::F7),
-            "F8" => Some(Keycode::F8),
-            "F9" => Some(Keycode::F9),
-            "F10" => Some(Keycode::F10),
-            "F11" => Some(Keycode::F11),
-            "F12" => Some(Keycode::F12),
-            _ => None,
-        }
-    }
-
-    fn str_to_key(key_str: &str) -> Option<Key> {
-        match key_str {
-            "1" => Some(Key::Unicode('1')),
-            "2" => Some(Key::Unicode('2')),
-            "3" => Some(Key::Unicode('3')),
-            "4" => Some(Key::Unicode('4')),
-            "5" => Some(Key::Unicode('5')),
-            "q" | "Q" => Some(Key::Unicode('q')),
-            "w" | "W" => Some(Key::Unicode('w')),
-            "e" | "E" => Some(Key::Unicode('e')),
-            "r" | "R" => Some(Key::Unicode('r')),
-            "t" | "T" => Some(Key::Unicode('t')),
-            _ => None,
-        }
-    }
-
-    fn calculate_interval(base_ms: u64, variance_ms: u64) -> Duration {
+    /// Count a failed key press or click and stop the macro once a breaker trips
+    ///
+    /// Returns true when this failure tripped the backend breaker, after which
+    /// the caller disables every other macro too.
+    fn record_input_failure(
+        label: &str,
+        error: &str,
+        enabled: &AtomicBool,
+        macro_health: &CircuitBreaker,
+        backend_health: &CircuitBreaker,
+    ) -> bool {
+        warn!("Macro '{}': Input failed: {}", label, error);
+
+        if macro_health.record_failure(error) {
+            enabled.store(false, Ordering::Relaxed);
+            error!(
+                "Macro '{}' stopped after {} consecutive failures (last error: {}). Press its hotkey or reset it to retry",
+                label, MAX_MACRO_FAILURES, error
+            );
+        }
+
+        if backend_health.record_failure(error) {
+            enabled.store(false, Ordering::Relaxed);
+            error!(
+                "Input backend failed {} times in a row; all macros stopped (last error: {})",
+                MAX_BACKEND_FAILURES, error
+            );
+            return true;
+        }
+        false
+    }
+
+    /// Disable every macro, e.g. once the backend breaker tripped
+    ///
+    /// They stay disabled after a reset until toggled on again.
+    async fn disable_all(macros: &RwLock<Vec<MacroHandle>>) {
+        for handle in macros.read().await.iter() {
+            handle.enabled.store(false, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn calculate_interval(base_ms: u64, variance_ms: u64) -> Duration {
         if variance_ms == 0 {
             return Duration::from_millis(base_ms);
         }
@@ -331,50 +948,204 @@ impl MacroEngineState {
 /// Simple macro engine for CLI mode
 pub struct MacroEngine {
     config: Config,
+    engine: MacroEngineState,
 }
 
 impl MacroEngine {
-    pub fn new(config: Config) -> Self {
-        Self { config }
+    pub fn new(
+        config: Config,
+        profiles: ProfileStore,
+        overrides: Overrides,
+        dry_run: bool,
+    ) -> Self {
+        let mut engine = MacroEngineState::new(profiles, overrides);
+        engine.dry_run = dry_run;
+        Self { config, engine }
     }
 
-    pub async fn run(&mut self) -> Result<()> {
-        info!(
-            "Starting POE2 Macro Engine with {} macro(s)...",
-            self.config.macros.len()
-        );
+    /// Shared engine state, e.g. for the config watcher
+    pub fn state(&self) -> &MacroEngineState {
+        &self.engine
+    }
+
+    /// Start the macros without waiting for them to stop
+    pub async fn start(&self) -> Result<()> {
         info!("TIP: On laptops, you may need to press Fn+F# to toggle");
 
-        if self.config.macros.is_empty() {
-            return Err(anyhow::anyhow!("No macros configured"));
-        }
+        // The engine validates the whole config before spawning anything
+        self.engine.start(self.config.clone()).await
+    }
 
-        // Spawn a task for each macro
-        let mut handles = vec![];
-        let running = Arc::new(AtomicBool::new(true));
+    pub async fn run(&mut self) -> Result<()> {
+        self.start().await?;
 
-        for (idx, macro_config) in self.config.macros.iter().enumerate() {
-            let macro_config = macro_config.clone();
-            let enabled = Arc::new(AtomicBool::new(macro_config.enabled_by_default));
-            let running_clone = running.clone();
+        // Macros run until the session limit stops the engine
+        self.engine.stopped().await;
+        Ok(())
+    }
+}
 
-            let handle = tokio::spawn(async move {
-                if let Err(e) =
-                    MacroEngineState::run_single_macro(idx, macro_config, enabled, running_clone)
-                        .await
-                {
-                    warn!("Macro {} error: {}", idx, e);
-                }
-            });
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaker_trips_once_at_threshold() {
+        let breaker = CircuitBreaker::new(3);
+        assert!(!breaker.record_failure("a"));
+        assert!(!breaker.record_failure("b"));
+        assert!(breaker.record_failure("c"));
+        assert!(breaker.is_tripped());
+        // Further failures don't report a new trip
+        assert!(!breaker.record_failure("d"));
+        assert_eq!(breaker.last_error().as_deref(), Some("d"));
+    }
+
+    #[test]
+    fn success_restarts_the_count() {
+        let breaker = CircuitBreaker::new(2);
+        assert!(!breaker.record_failure("a"));
+        breaker.record_success();
+        assert!(!breaker.record_failure("b"));
+        assert!(!breaker.is_tripped());
+        assert!(breaker.record_failure("c"));
+    }
 
-            handles.push(handle);
+    #[test]
+    fn reset_clears_trip_and_error() {
+        let breaker = CircuitBreaker::new(5);
+        breaker.trip("no display");
+        assert!(breaker.is_tripped());
+        assert_eq!(breaker.last_error().as_deref(), Some("no display"));
+
+        breaker.reset();
+        assert!(!breaker.is_tripped());
+        assert_eq!(breaker.last_error(), None);
+        for _ in 0..4 {
+            assert!(!breaker.record_failure("again"));
         }
+        assert!(breaker.record_failure("again"));
+    }
 
-        // Wait for all macros (they run forever)
-        for handle in handles {
-            let _ = handle.await;
+    #[test]
+    fn backend_trip_is_reported_to_the_caller() {
+        let enabled = AtomicBool::new(true);
+        let macro_health = CircuitBreaker::new(MAX_MACRO_FAILURES);
+        let backend_health = CircuitBreaker::new(2);
+
+        assert!(!MacroEngineState::record_input_failure(
+            "m",
+            "e",
+            &enabled,
+            &macro_health,
+            &backend_health
+        ));
+        assert!(enabled.load(Ordering::Relaxed));
+        assert!(MacroEngineState::record_input_failure(
+            "m",
+            "e",
+            &enabled,
+            &macro_health,
+            &backend_health
+        ));
+        assert!(!enabled.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn disable_all_stops_every_macro() {
+        let config = Config::default();
+        let handles: Vec<MacroHandle> = config.macros.iter().map(MacroHandle::new).collect();
+        assert!(!handles.is_empty());
+        for handle in &handles {
+            handle.enabled.store(true, Ordering::Relaxed);
         }
+        let macros = RwLock::new(handles);
 
-        Ok(())
+        MacroEngineState::disable_all(&macros).await;
+        assert!(macros
+            .read()
+            .await
+            .iter()
+            .all(|h| !h.enabled.load(Ordering::Relaxed)));
+    }
+
+    /// A dry-run engine running two macros from a profile in a temp directory
+    async fn running_engine() -> (tempfile::TempDir, MacroEngineState) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(
+            &path,
+            "version: 2\nmacros:\n  - id: flask\n    key: '1'\n    interval_ms: 1000\n    toggle_hotkey: F9\n  - id: mana\n    key: '2'\n    interval_ms: 1500\n    toggle_hotkey: F10\n",
+        )
+        .unwrap();
+        let mut engine = MacroEngineState::new(ProfileStore::open(&path), Overrides::default());
+        engine.dry_run = true;
+        let config = engine.profiles().load("default").unwrap();
+        engine.start(config).await.unwrap();
+        (dir, engine)
+    }
+
+    async fn status_of(engine: &MacroEngineState, id: &str) -> Option<MacroStatus> {
+        engine.get_status().await.into_iter().find(|m| m.id == id)
+    }
+
+    #[tokio::test]
+    async fn updated_macros_stay_enabled() {
+        let (_dir, engine) = running_engine().await;
+        engine.toggle_macro("flask").await.unwrap();
+
+        let config = engine.profiles().load("default").unwrap();
+        let retuned = KeyMacro {
+            key: "3".to_string(),
+            ..config.macros[0].clone()
+        };
+        let config = engine.update_macro("flask", retuned).await.unwrap();
+        assert_eq!(config.macros[0].key, "3");
+
+        let flask = status_of(&engine, "flask").await.unwrap();
+        assert!(flask.enabled);
+        assert_eq!(flask.key, "3");
+        assert!(!status_of(&engine, "mana").await.unwrap().enabled);
+        engine.stop().await;
+    }
+
+    #[tokio::test]
+    async fn unknown_ids_are_errors() {
+        let (_dir, engine) = running_engine().await;
+        let config = engine.profiles().load("default").unwrap();
+
+        for result in [
+            engine
+                .update_macro("nope", config.macros[0].clone())
+                .await
+                .map(drop),
+            engine.remove_macro("nope").await.map(drop),
+            engine.toggle_macro("nope").await,
+        ] {
+            let error = result.unwrap_err().to_string();
+            assert!(error.contains("No macro with id 'nope'"), "{}", error);
+        }
+        assert_eq!(engine.get_status().await.len(), 2);
+        engine.stop().await;
+    }
+
+    #[tokio::test]
+    async fn removed_macros_stop_their_task() {
+        let (_dir, engine) = running_engine().await;
+        let mana = {
+            let macros = engine.macros.read().await;
+            macros[MacroEngineState::find(&macros, "mana").unwrap()].clone()
+        };
+        assert!(mana.running.load(Ordering::Relaxed));
+
+        let config = engine.remove_macro("mana").await.unwrap();
+        assert_eq!(config.macros.len(), 1);
+        assert!(!mana.running.load(Ordering::Relaxed));
+        assert!(status_of(&engine, "mana").await.is_none());
+        // The rest keep running
+        assert!(engine.macros.read().await[0]
+            .running
+            .load(Ordering::Relaxed));
+        engine.stop().await;
     }
 }
@@ -4,35 +4,98 @@
 mod cli;
 mod commands;
 mod config;
+mod daemon;
+mod diff;
+mod duration;
+mod format;
+mod game_keybinds;
+mod inheritance;
+mod ipc;
+mod keys;
 mod macro_engine;
+mod migration;
+mod overrides;
+mod paths;
+mod profiles;
+mod schema;
+mod session;
+mod share;
+mod storage;
+mod templates;
+mod validation;
+mod watcher;
+mod yaml_edit;
 
 use commands::ConfigCache;
 use macro_engine::MacroEngineState;
-use tauri::Manager;
+use overrides::Overrides;
+use profiles::ProfileStore;
+use tauri::{Emitter, Manager};
+use watcher::ConfigWatcher;
 
 #[tokio::main]
 async fn main() {
+    let cli = cli::Cli::parse_args();
+
     // Initialize tracing
+    // Logs go to stderr so CLI output such as `schema` can be piped
     tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
+        .with_max_level(tracing::Level::from(cli.log_level))
+        .with_writer(std::io::stderr)
         .init();
 
-    // Check if CLI mode is requested
-    if cli::is_cli_mode() {
-        // Run CLI mode
-        if let Err(e) = cli::run_cli().await {
+    // Resolve the config file once for both modes
+    let location = match paths::resolve_config_path(cli.config.clone()) {
+        Ok(location) => location,
+        Err(e) => {
             eprintln!("Error: {}", e);
-            std::process::exit(1);
+            std::process::exit(cli::EXIT_FAILURE);
+        }
+    };
+
+    // Config values overridden for this run, in either mode; the GUI skips
+    // malformed environment variables instead of refusing to start
+    let overrides = match Overrides::collect(&cli.set, cli.command.is_some()) {
+        Ok(overrides) => overrides,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(cli::EXIT_FAILURE);
+        }
+    };
+
+    // Any subcommand runs without the GUI
+    if cli.command.is_some() {
+        if let Err(e) = cli::run_cli(cli, location, overrides).await {
+            eprintln!("Error: {}", e);
+            std::process::exit(cli::exit_code(&e));
         }
         return;
     }
 
     // Run GUI mode
+    let profiles = ProfileStore::open(&location.path);
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .manage(MacroEngineState::new())
-        .manage(ConfigCache::new())
+        .manage(MacroEngineState::new(profiles.clone(), overrides))
+        .manage(ConfigCache::new(profiles))
+        .manage(location)
         .setup(|app| {
+            // Hot-reload the config file and tell the UI when it changed
+            let engine = app.state::<MacroEngineState>().inner().clone();
+            let cache = app.state::<ConfigCache>().inner().clone();
+            let handle = app.handle().clone();
+            let watcher = ConfigWatcher::start(engine, move |config| {
+                let cache = cache.clone();
+                let handle = handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Some(loaded) = cache.update(config).await {
+                        let _ = handle.emit("config-changed", loaded);
+                    }
+                });
+            })?;
+            app.manage(watcher);
+
             // Get the main window
             let window = app.get_webview_window("main").unwrap();
 
@@ -47,12 +110,34 @@ async fn main() {
         })
         .invoke_handler(tauri::generate_handler![
             commands::load_config,
+            commands::reload_config,
+            commands::get_load_report,
+            commands::get_config_location,
             commands::save_config,
             commands::validate_config,
+            commands::get_game_keybinds,
+            commands::get_config_schema,
             commands::start_macro_engine,
             commands::stop_macro_engine,
             commands::get_macro_status,
             commands::toggle_macro,
+            commands::update_macro,
+            commands::add_macro,
+            commands::remove_macro,
+            commands::reset_macro_errors,
+            commands::get_session_status,
+            commands::list_profiles,
+            commands::switch_profile,
+            commands::create_profile,
+            commands::delete_profile,
+            commands::export_profile,
+            commands::preview_profile_code,
+            commands::import_profile_code,
+            commands::diff_configs,
+            commands::diff_profile_code,
+            commands::list_templates,
+            commands::preview_template,
+            commands::create_profile_from_template,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
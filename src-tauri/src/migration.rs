@@ -0,0 +1,118 @@
+//! Config schema versions and the migration chain that upgrades older files
+//!
+//! Migrations operate on the raw YAML document before it is deserialized, so
+//! each step only has to know the shape of the version it upgrades from.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+
+/// Schema version written by this build
+pub const CURRENT_VERSION: u32 = 2;
+
+/// A single upgrade step from `from` to `from + 1`
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&mut Mapping, &mut Vec<String>),
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "make action_type explicit on every macro",
+    apply: explicit_action_type,
+}];
+
+/// What a migration run changed
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub changes: Vec<String>,
+    /// Where the original file was backed up before being rewritten
+    pub backup_path: Option<String>,
+}
+
+pub fn current_version() -> u32 {
+    CURRENT_VERSION
+}
+
+/// Upgrade a raw config document to the current version in place
+///
+/// Returns `None` when the document is already current. Files without a
+/// `version` field predate versioning and are treated as version 1.
+pub fn migrate(document: &mut Value) -> Result<Option<MigrationReport>> {
+    let root = document
+        .as_mapping_mut()
+        .ok_or_else(|| anyhow::anyhow!("Config must be a YAML mapping"))?;
+
+    let from_version = match root.get("version") {
+        None => 1,
+        Some(value) => value
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| anyhow::anyhow!("Config version must be a positive integer"))?,
+    };
+
+    if from_version > CURRENT_VERSION {
+        return Err(anyhow::anyhow!(
+            "Config version {} is newer than this build supports (version {}); please update POE2 Macro Buddy",
+            from_version,
+            CURRENT_VERSION
+        ));
+    }
+    if from_version == CURRENT_VERSION {
+        return Ok(None);
+    }
+
+    let mut changes = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from_version) {
+        changes.push(format!(
+            "v{} → v{}: {}",
+            migration.from,
+            migration.from + 1,
+            migration.description
+        ));
+        (migration.apply)(root, &mut changes);
+    }
+    root.insert("version".into(), CURRENT_VERSION.into());
+
+    Ok(Some(MigrationReport {
+        from_version,
+        to_version: CURRENT_VERSION,
+        changes,
+        backup_path: None,
+    }))
+}
+
+/// v1 → v2: files written before mouse support had no `action_type`
+fn explicit_action_type(root: &mut Mapping, changes: &mut Vec<String>) {
+    let Some(macros) = root.get_mut("macros").and_then(Value::as_sequence_mut) else {
+        return;
+    };
+
+    for (idx, entry) in macros.iter_mut().enumerate() {
+        let Some(macro_map) = entry.as_mapping_mut() else {
+            continue;
+        };
+        if macro_map.contains_key("action_type") {
+            continue;
+        }
+
+        let has_key = macro_map
+            .get("key")
+            .and_then(Value::as_str)
+            .is_some_and(|key| !key.is_empty());
+        let action_type = if !has_key && macro_map.contains_key("mouse_button") {
+            "mouse"
+        } else {
+            "keyboard"
+        };
+
+        macro_map.insert("action_type".into(), action_type.into());
+        changes.push(format!(
+            "macros[{}].action_type set to {}",
+            idx, action_type
+        ));
+    }
+}
@@ -0,0 +1,371 @@
+//! One-off config values given on the command line or in the environment
+//!
+//! `--set macros.life_flask.interval_ms=900` and
+//! `POE2MB_MACROS__0__ENABLED_BY_DEFAULT=true` change a single field of
+//! whatever config the engine runs, without touching the file. Macros are
+//! addressed by index, id or name, ignoring case and punctuation (`life_flask`
+//! finds `Life Flask`, since environment variable names can't contain spaces
+//! or `-`). Values are read as YAML
+//! scalars, so `900`, `true` and `1.5s` get their natural types.
+//!
+//! Overrides only apply to the running engine: the GUI shows and saves the
+//! values from the file.
+
+use anyhow::Result;
+use serde_yaml::{Mapping, Value};
+use std::env;
+use std::fmt;
+use tracing::warn;
+
+use crate::config::{self, Config};
+use crate::format;
+use crate::inheritance::{INTERVAL_KEY, MILLIS_TIMING_KEYS};
+
+/// Prefix of environment variables that override config fields
+pub const ENV_PREFIX: &str = "POE2MB_";
+
+/// Separator between path segments in environment variable names
+const ENV_SEPARATOR: &str = "__";
+
+/// Where an override came from, for log messages
+#[derive(Debug, Clone, PartialEq)]
+pub enum OverrideSource {
+    /// A `--set path=value` argument
+    Argument,
+    /// An environment variable, by name
+    Environment(String),
+}
+
+impl fmt::Display for OverrideSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Argument => write!(f, "--set"),
+            Self::Environment(name) => write!(f, "${}", name),
+        }
+    }
+}
+
+/// A single field set from outside the config file
+#[derive(Debug, Clone)]
+pub struct Override {
+    /// Field path as given, e.g. `macros.life_flask.interval_ms`
+    pub path: String,
+    segments: Vec<String>,
+    pub value: Value,
+    pub source: OverrideSource,
+}
+
+impl Override {
+    /// Parse a `--set path=value` argument
+    pub fn from_argument(arg: &str) -> Result<Self> {
+        let (path, value) = arg
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("--set expects <path>=<value>, got '{}'", arg))?;
+        Self::new(path.trim(), value, OverrideSource::Argument)
+    }
+
+    /// Parse a `POE2MB_<PATH>=<value>` environment variable, if it is one
+    pub fn from_env(name: &str, value: &str) -> Option<Result<Self>> {
+        let path = name.strip_prefix(ENV_PREFIX)?;
+        let path = path
+            .split(ENV_SEPARATOR)
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join(".");
+        Some(Self::new(
+            &path,
+            value,
+            OverrideSource::Environment(name.to_string()),
+        ))
+    }
+
+    fn new(path: &str, value: &str, source: OverrideSource) -> Result<Self> {
+        // `macros[0].key` (as in validation messages) is the same as `macros.0.key`
+        let segments: Vec<String> = path
+            .replace('[', ".")
+            .replace(']', "")
+            .split('.')
+            .map(str::to_string)
+            .collect();
+        if segments.iter().any(String::is_empty) {
+            return Err(anyhow::anyhow!(
+                "Invalid override path '{}' (from {})",
+                path,
+                source
+            ));
+        }
+
+        // Anything that isn't valid YAML on its own is taken as plain text
+        let value = serde_yaml::from_str(value).unwrap_or_else(|_| Value::from(value));
+        Ok(Self {
+            path: path.to_string(),
+            segments,
+            value,
+            source,
+        })
+    }
+}
+
+/// A field an override changed, for log messages
+#[derive(Debug, Clone)]
+pub struct Applied {
+    pub path: String,
+    pub source: OverrideSource,
+    /// Value from the config before the override, if the field was set
+    pub previous: Option<Value>,
+    pub value: Value,
+}
+
+impl fmt::Display for Applied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.path, format::one_line(&self.value))?;
+        if let Some(previous) = &self.previous {
+            write!(f, " (was {})", format::one_line(previous))?;
+        }
+        write!(f, " from {}", self.source)
+    }
+}
+
+/// Every override in effect, applied in order so later ones win
+///
+/// Environment variables come first, so `--set` wins over them.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    list: Vec<Override>,
+}
+
+impl Overrides {
+    /// Collect `POE2MB_*` environment variables and the given `--set` values
+    ///
+    /// A malformed variable is an error when `strict`, and otherwise skipped
+    /// with a warning, so a stray variable can't keep the GUI from starting.
+    pub fn collect(set_args: &[String], strict: bool) -> Result<Self> {
+        Self::from_sources(env::vars().collect(), set_args, strict)
+    }
+
+    fn from_sources(
+        mut variables: Vec<(String, String)>,
+        set_args: &[String],
+        strict: bool,
+    ) -> Result<Self> {
+        variables.sort();
+
+        let mut list = Vec::new();
+        for (name, value) in &variables {
+            match Override::from_env(name, value) {
+                Some(Ok(parsed)) => list.push(parsed),
+                Some(Err(e)) if !strict => warn!("Ignoring ${}: {}", name, e),
+                Some(Err(e)) => return Err(e),
+                None => {}
+            }
+        }
+        for arg in set_args {
+            list.push(Override::from_argument(arg)?);
+        }
+        Ok(Self { list })
+    }
+
+    /// `config` with every override applied, and what each one changed
+    ///
+    /// Fails when a path doesn't name a config field or a value has the wrong
+    /// type; the result still needs validating like any other config.
+    /// Overrides of a macro the config doesn't have are skipped with a
+    /// warning, since they may be meant for another profile.
+    pub fn apply(&self, config: &Config) -> Result<(Config, Vec<Applied>)> {
+        if self.list.is_empty() {
+            return Ok((config.clone(), Vec::new()));
+        }
+
+        let mut document = serde_yaml::to_value(config)?;
+        let entries: Vec<&Override> = self
+            .list
+            .iter()
+            .filter(|entry| {
+                let found = has_macro(&document, entry);
+                if !found {
+                    warn!(
+                        "Skipping override {} (from {}): no macro '{}' in this profile",
+                        entry.path, entry.source, entry.segments[1]
+                    );
+                }
+                found
+            })
+            .collect();
+        let mut applied = Vec::new();
+        for entry in &entries {
+            let (parent, key) = locate(&mut document, entry)?;
+            // An `interval` replaces the millisecond fields and vice versa
+            if key == INTERVAL_KEY {
+                for field in MILLIS_TIMING_KEYS {
+                    parent.remove(*field);
+                }
+            } else if MILLIS_TIMING_KEYS.contains(&key.as_str()) {
+                parent.remove(INTERVAL_KEY);
+            }
+            let previous = parent.insert(Value::from(key), entry.value.clone());
+            applied.push(Applied {
+                path: entry.path.clone(),
+                source: entry.source.clone(),
+                previous: previous.filter(|v| !v.is_null()),
+                value: entry.value.clone(),
+            });
+        }
+
+        let mut result: Config = serde_yaml::from_value(document)
+            .map_err(|e| anyhow::anyhow!("Overrides don't fit the config: {}", e))?;
+        result.assign_ids();
+        check_fields(&entries, &result)?;
+        Ok((result, applied))
+    }
+}
+
+/// Reject overrides of fields the config doesn't have, which would be ignored
+fn check_fields(entries: &[&Override], result: &Config) -> Result<()> {
+    let mut document = serde_yaml::to_value(result)?;
+    for entry in entries {
+        let (parent, key) = locate(&mut document, entry)?;
+        let known =
+            parent.contains_key(key.as_str()) || key == INTERVAL_KEY || entry.value.is_null();
+        if !known {
+            return Err(anyhow::anyhow!(
+                "Override {} (from {}) doesn't name a config field",
+                entry.path,
+                entry.source
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a `macros.<macro>...` override finds its macro; other paths always do
+fn has_macro(document: &Value, entry: &Override) -> bool {
+    let [first, selector, _, ..] = entry.segments.as_slice() else {
+        return true;
+    };
+    let items = match document.get("macros").and_then(Value::as_sequence) {
+        Some(items) if first == "macros" => items,
+        _ => return true,
+    };
+    match selector.parse::<usize>() {
+        Ok(idx) => idx < items.len(),
+        Err(_) => items.iter().any(|item| matches(item, selector)),
+    }
+}
+
+/// Whether a macro's id or name is `selector`, ignoring case and punctuation
+fn matches(item: &Value, selector: &str) -> bool {
+    ["id", "name"].iter().any(|field| {
+        item.get(field)
+            .and_then(Value::as_str)
+            .is_some_and(|value| config::slug(value) == config::slug(selector))
+    })
+}
+
+/// Mapping holding the field an override sets, and the field's name
+fn locate<'a>(document: &'a mut Value, entry: &Override) -> Result<(&'a mut Mapping, String)> {
+    let not_found = |at: &str| {
+        anyhow::anyhow!(
+            "Override {} (from {}): nothing at '{}'",
+            entry.path,
+            entry.source,
+            at
+        )
+    };
+
+    let (key, parents) = entry
+        .segments
+        .split_last()
+        .expect("override paths have at least one segment");
+    let mut current = document;
+    for segment in parents {
+        current = match current {
+            Value::Mapping(mapping) => mapping
+                .get_mut(segment.as_str())
+                .ok_or_else(|| not_found(segment))?,
+            Value::Sequence(items) => {
+                let position = match segment.parse::<usize>() {
+                    Ok(idx) => idx,
+                    Err(_) => items
+                        .iter()
+                        .position(|item| matches(item, segment))
+                        .ok_or_else(|| not_found(segment))?,
+                };
+                items.get_mut(position).ok_or_else(|| not_found(segment))?
+            }
+            _ => return Err(not_found(segment)),
+        };
+    }
+
+    match current {
+        Value::Mapping(mapping) => Ok((mapping, key.clone())),
+        _ => Err(not_found(key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        let yaml = "macros:\n  - name: Life Flask\n    key: \"1\"\n    interval_ms: 1000\n    toggle_hotkey: F9\n";
+        let (config, _) = Config::parse(yaml, crate::format::ConfigFormat::Yaml).unwrap();
+        config
+    }
+
+    fn variables(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn malformed_variables_are_skipped_unless_strict() {
+        let vars = variables(&[
+            ("POE2MB_MACROS____KEY", "2"),
+            ("POE2MB_MACROS__LIFE_FLASK__INTERVAL_MS", "900"),
+            ("HOME", "/root"),
+        ]);
+        assert!(Overrides::from_sources(vars.clone(), &[], true).is_err());
+
+        let overrides = Overrides::from_sources(vars, &[], false).unwrap();
+        let (config, applied) = overrides.apply(&config()).unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(config.macros[0].interval_ms, 900);
+    }
+
+    #[test]
+    fn arguments_win_over_variables() {
+        let vars = variables(&[("POE2MB_MACROS__0__INTERVAL_MS", "900")]);
+        let set = ["macros[0].interval=1.2s..1.8s".to_string()];
+        let overrides = Overrides::from_sources(vars, &set, true).unwrap();
+        let (config, applied) = overrides.apply(&config()).unwrap();
+        assert_eq!(applied[1].source, OverrideSource::Argument);
+        assert_eq!(config.macros[0].interval_ms, 1500);
+        assert_eq!(config.macros[0].random_variance_ms, 300);
+        assert!(Overrides::from_sources(Vec::new(), &["no_equals".to_string()], false).is_err());
+    }
+
+    #[test]
+    fn overrides_of_missing_macros_are_skipped() {
+        let set = [
+            "macros.mana_flask.interval_ms=700".to_string(),
+            "macros[3].key=r".to_string(),
+            "macros.life_flask.key=e".to_string(),
+        ];
+        let overrides = Overrides::from_sources(Vec::new(), &set, true).unwrap();
+        let (config, applied) = overrides.apply(&config()).unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(config.macros[0].key, "e");
+    }
+
+    #[test]
+    fn unknown_fields_still_fail() {
+        for arg in [
+            "macros.life_flask.intervall=5",
+            "sesion.max_runtime_minutes=5",
+        ] {
+            let overrides = Overrides::from_sources(Vec::new(), &[arg.to_string()], true).unwrap();
+            assert!(overrides.apply(&config()).is_err(), "{}", arg);
+        }
+    }
+}
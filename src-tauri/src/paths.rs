@@ -0,0 +1,114 @@
+//! Resolution of the main config file location
+
+use anyhow::Result;
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::format::ConfigFormat;
+
+/// Environment variable that points at a config file
+pub const CONFIG_ENV: &str = "POE2_MACRO_CONFIG";
+
+const CONFIG_FILE_STEM: &str = "config";
+const APP_DIR_NAME: &str = "poe2-macro-buddy";
+
+/// Where the config path came from
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    /// `--config <path>` on the command line
+    Argument,
+    /// The `POE2_MACRO_CONFIG` environment variable
+    Environment,
+    /// A `config.yaml` (or `.toml`/`.json`) next to the executable (portable install)
+    Portable,
+    /// The per-user config directory of the OS
+    ConfigDir,
+}
+
+/// The resolved main config file and how it was found
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigLocation {
+    pub path: PathBuf,
+    pub source: ConfigSource,
+}
+
+/// Resolve the main config file
+///
+/// Checked in order: the `--config` argument, the `POE2_MACRO_CONFIG`
+/// environment variable, an existing `config.yaml` next to the executable, and
+/// finally `config.yaml` in the OS config directory (e.g.
+/// `~/.config/poe2-macro-buddy` or `%APPDATA%\poe2-macro-buddy`). In both
+/// directories an existing `config.toml` or `config.json` is used instead.
+pub fn resolve_config_path(argument: Option<PathBuf>) -> Result<ConfigLocation> {
+    let location = find_config_path(argument)?;
+    info!(
+        "Using config file: {} ({:?})",
+        location.path.display(),
+        location.source
+    );
+    Ok(location)
+}
+
+fn find_config_path(argument: Option<PathBuf>) -> Result<ConfigLocation> {
+    if let Some(path) = argument {
+        return Ok(ConfigLocation {
+            path: absolute(path)?,
+            source: ConfigSource::Argument,
+        });
+    }
+
+    if let Some(path) = env::var_os(CONFIG_ENV).filter(|value| !value.is_empty()) {
+        return Ok(ConfigLocation {
+            path: absolute(PathBuf::from(path))?,
+            source: ConfigSource::Environment,
+        });
+    }
+
+    if let Some(exe_dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+    {
+        if let Some(portable) = existing_config(&exe_dir) {
+            return Ok(ConfigLocation {
+                path: portable,
+                source: ConfigSource::Portable,
+            });
+        }
+    }
+
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine the user config directory"))?
+        .join(APP_DIR_NAME);
+    fs::create_dir_all(&config_dir)?;
+
+    Ok(ConfigLocation {
+        path: existing_config(&config_dir)
+            .unwrap_or_else(|| config_file(&config_dir, ConfigFormat::Yaml)),
+        source: ConfigSource::ConfigDir,
+    })
+}
+
+fn config_file(dir: &Path, format: ConfigFormat) -> PathBuf {
+    dir.join(format!("{}.{}", CONFIG_FILE_STEM, format.extension()))
+}
+
+/// Config file in `dir` in any supported format, YAML first
+fn existing_config(dir: &Path) -> Option<PathBuf> {
+    ConfigFormat::ALL
+        .iter()
+        .map(|&format| config_file(dir, format))
+        .find(|path| path.is_file())
+}
+
+/// Make a user-supplied path independent of the current directory
+fn absolute(path: PathBuf) -> Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Ok(env::current_dir()?.join(path))
+    }
+}
@@ -0,0 +1,593 @@
+//! Named profiles stored next to the main config file
+//!
+//! The main config file is the `default` profile and also records which
+//! profile is active. Every other profile is a full config document in the
+//! `profiles/` directory beside it, e.g. `profiles/witch.yaml`. Profiles may
+//! use any config format; new ones take the format of the main file.
+//!
+//! Any profile, including the main file, may `extends:` another profile or a
+//! base file (see [`crate::inheritance`]).
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+use crate::config::{Config, LoadReport};
+use crate::format::ConfigFormat;
+use crate::game_keybinds::{self, ResolvedAction};
+use crate::inheritance::{self, Origin, Resolved};
+use crate::storage;
+use crate::templates::{self, Template};
+use crate::validation::ValidationReport;
+
+/// Name of the profile stored in the main config file
+pub const DEFAULT_PROFILE: &str = "default";
+
+const PROFILES_DIR: &str = "profiles";
+
+/// Available profiles and the one currently active
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<String>,
+}
+
+/// Profiles belonging to one main config file, shared by the GUI, CLI and engine
+#[derive(Clone)]
+pub struct ProfileStore {
+    config_path: PathBuf,
+    active: Arc<RwLock<String>>,
+}
+
+impl ProfileStore {
+    /// Open the profiles of a main config file
+    ///
+    /// Only the active profile pointer is read here; the main file is neither
+    /// created nor migrated until it is actually loaded.
+    pub fn open<P: AsRef<Path>>(config_path: P) -> Self {
+        let config_path = config_path.as_ref().to_path_buf();
+        let pointer = fs::read_to_string(&config_path)
+            .ok()
+            .and_then(|content| {
+                ConfigFormat::from_path(&config_path)
+                    .and_then(|format| format.parse(&content))
+                    .ok()
+            })
+            .and_then(|document| {
+                document
+                    .get("active_profile")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            });
+
+        let store = Self {
+            config_path,
+            active: Arc::new(RwLock::new(DEFAULT_PROFILE.to_string())),
+        };
+
+        if let Some(active) = pointer {
+            if store.exists(&active) {
+                *store.active.write().unwrap() = active;
+            } else {
+                warn!("Active profile '{}' not found, using default", active);
+            }
+        }
+
+        store
+    }
+
+    pub fn profiles_dir(&self) -> PathBuf {
+        self.config_path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(PROFILES_DIR)
+    }
+
+    /// File holding the given profile, in whichever format it was written
+    pub fn path_of(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_PROFILE {
+            return self.config_path.clone();
+        }
+
+        let dir = self.profiles_dir();
+        ConfigFormat::ALL
+            .iter()
+            .map(|format| dir.join(format!("{}.{}", name, format.extension())))
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| {
+                let format =
+                    ConfigFormat::from_path(&self.config_path).unwrap_or(ConfigFormat::Yaml);
+                dir.join(format!("{}.{}", name, format.extension()))
+            })
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.path_of(name).is_file()
+    }
+
+    /// Names of all profiles, `default` first and the rest sorted
+    pub fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+
+        if let Ok(entries) = fs::read_dir(self.profiles_dir()) {
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().is_none() || ConfigFormat::from_path(&path).is_err() {
+                    continue;
+                }
+                if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                    if check_name(name).is_ok() {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+        }
+
+        names.sort();
+        names.dedup();
+        names.insert(0, DEFAULT_PROFILE.to_string());
+        Ok(names)
+    }
+
+    pub fn summary(&self) -> Result<ProfileList> {
+        Ok(ProfileList {
+            active: self.active(),
+            profiles: self.list()?,
+        })
+    }
+
+    pub fn active(&self) -> String {
+        self.active.read().unwrap().clone()
+    }
+
+    pub fn active_path(&self) -> PathBuf {
+        self.path_of(&self.active())
+    }
+
+    pub fn load(&self, name: &str) -> Result<Config> {
+        self.load_with_report(name).map(|(config, _)| config)
+    }
+
+    /// Load a profile to use it, merged with whatever it extends
+    ///
+    /// Only profiles that extend nothing are upgraded or restored from a backup
+    /// on disk, and a missing main file is created; layered files are upgraded
+    /// in memory. Use [`ProfileStore::read`] to look at a profile without
+    /// writing anything.
+    pub fn load_with_report(&self, name: &str) -> Result<(Config, LoadReport)> {
+        check_profile(name)?;
+        let path = self.path_of(name);
+        if name == DEFAULT_PROFILE && !path.exists() {
+            return Ok((Config::load_or_default(&path)?, LoadReport::default()));
+        }
+        if !self.exists(name) {
+            return Err(anyhow::anyhow!("Profile '{}' does not exist", name));
+        }
+        if self.extends(name).is_some() {
+            return Ok((self.resolve(name)?.to_config()?, LoadReport::default()));
+        }
+        let (config, mut report) = Config::load_with_report(&path)?;
+        config.persist_load(&path, &mut report)?;
+        Ok((config, report))
+    }
+
+    /// Read a profile without changing anything on disk, e.g. for hot reload
+    pub fn read(&self, name: &str) -> Result<Config> {
+        check_profile(name)?;
+        if self.extends(name).is_some() {
+            return self.resolve(name)?.to_config();
+        }
+        Config::read(self.path_of(name))
+    }
+
+    /// [`ProfileStore::load`] without writing anything, for `--dry-run`
+    ///
+    /// Nothing is upgraded or restored on disk, and a missing main file gives
+    /// the default config instead of being created.
+    pub fn load_read_only(&self, name: &str) -> Result<Config> {
+        check_profile(name)?;
+        if !self.exists(name) {
+            if name == DEFAULT_PROFILE {
+                return templates::find(templates::DEFAULT_TEMPLATE)?.config();
+            }
+            return Err(anyhow::anyhow!("Profile '{}' does not exist", name));
+        }
+        self.read(name)
+    }
+
+    /// Merge a profile with everything it extends
+    pub fn resolve(&self, name: &str) -> Result<Resolved> {
+        let origin = Origin {
+            profile: Some(name.to_string()),
+            path: self.path_of(name),
+        };
+        inheritance::resolve(origin, &|reference, from| self.locate(reference, from))
+    }
+
+    /// Files a profile is built from, for watching
+    pub fn layer_paths(&self, name: &str) -> Vec<PathBuf> {
+        match self.resolve(name) {
+            Ok(resolved) => resolved.layers.into_iter().map(|o| o.path).collect(),
+            Err(_) => vec![self.path_of(name)],
+        }
+    }
+
+    /// Point validation issues of the active profile at the file they came from
+    ///
+    /// Only done when `config` is still what the profile resolves to on disk,
+    /// so unsaved edits are never attributed to the wrong file.
+    pub fn annotate(&self, config: &Config, report: &mut ValidationReport) {
+        if let Ok(resolved) = self.resolve(&self.active()) {
+            if resolved.to_config().ok().as_ref() == Some(config) {
+                resolved.annotate(report);
+            }
+        }
+    }
+
+    /// Profiles whose own file extends the given one
+    pub fn dependents(&self, name: &str) -> Result<Vec<String>> {
+        let target = self.path_of(name);
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|other| other != name)
+            .filter(|other| {
+                self.extends(other).is_some_and(|reference| {
+                    self.locate(&reference, &self.path_of(other))
+                        .is_ok_and(|origin| origin.path == target)
+                })
+            })
+            .collect())
+    }
+
+    /// What a profile's own file extends, if anything
+    fn extends(&self, name: &str) -> Option<String> {
+        inheritance::read_layer(&self.path_of(name))
+            .ok()
+            .and_then(|document| inheritance::extends_of(&document).ok().flatten())
+    }
+
+    /// File an `extends` value in `from` refers to
+    ///
+    /// Values with a path separator or a file extension are paths relative to
+    /// the extending file; anything else names a profile.
+    fn locate(&self, reference: &str, from: &Path) -> Result<Origin> {
+        let is_path = reference.contains(['/', '\\']) || Path::new(reference).extension().is_some();
+        if is_path {
+            let path = from
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(reference);
+            if !path.is_file() {
+                return Err(anyhow::anyhow!(
+                    "extends {}, which does not exist",
+                    path.display()
+                ));
+            }
+            return Ok(Origin {
+                profile: None,
+                path,
+            });
+        }
+
+        if !self.exists(reference) {
+            return Err(anyhow::anyhow!(
+                "extends profile '{}', which does not exist",
+                reference
+            ));
+        }
+        Ok(Origin {
+            profile: Some(reference.to_string()),
+            path: self.path_of(reference),
+        })
+    }
+
+    /// Save a profile, keeping the active profile pointer when writing the main file
+    ///
+    /// A profile that extends another only stores how it differs from it.
+    pub fn save(&self, name: &str, config: &Config) -> Result<()> {
+        let mut config = config.clone();
+        if name == DEFAULT_PROFILE {
+            config.active_profile = self.active_pointer();
+        } else {
+            config.active_profile = None;
+            fs::create_dir_all(self.profiles_dir())?;
+        }
+        match self.extends(name) {
+            Some(reference) => self.save_overrides(name, &reference, &config),
+            None => config.save(self.path_of(name)),
+        }
+    }
+
+    fn save_overrides(&self, name: &str, reference: &str, config: &Config) -> Result<()> {
+        let path = self.path_of(name);
+        let parent = inheritance::resolve(self.locate(reference, &path)?, &|reference, from| {
+            self.locate(reference, from)
+        })?;
+        let own = inheritance::read_layer(&path)?;
+        let previous = self.resolve(name).and_then(|r| r.to_config()).ok();
+
+        let layer = inheritance::overrides(&parent, config, reference, &own, previous.as_ref())
+            .map_err(|e| anyhow::anyhow!("Profile '{}' extends '{}': {}", name, reference, e))?;
+        inheritance::write_layer(&path, &layer)
+    }
+
+    /// Create a new profile from an existing config
+    pub fn create(&self, name: &str, config: &Config) -> Result<()> {
+        check_name(name)?;
+        if self.exists(name) {
+            return Err(anyhow::anyhow!("Profile '{}' already exists", name));
+        }
+        self.save(name, config)?;
+        info!("Created profile '{}'", name);
+        Ok(())
+    }
+
+    /// Create a new profile from a built-in template, keeping its comments in YAML
+    pub fn create_from_template(&self, name: &str, template: &Template) -> Result<()> {
+        check_name(name)?;
+        if self.exists(name) {
+            return Err(anyhow::anyhow!("Profile '{}' already exists", name));
+        }
+        let path = self.path_of(name);
+        let content = template.render(ConfigFormat::from_path(&path)?)?;
+        fs::create_dir_all(self.profiles_dir())?;
+        storage::write_with_backup(&path, &content)?;
+        info!(
+            "Created profile '{}' from template '{}'",
+            name, template.name
+        );
+        Ok(())
+    }
+
+    /// Store an imported profile, refusing to overwrite one unless `replace` is set
+    ///
+    /// Importing over the default profile keeps the main file's own settings.
+    pub fn import(&self, name: &str, config: &Config, replace: bool) -> Result<()> {
+        if name != DEFAULT_PROFILE {
+            check_name(name)?;
+        }
+        if self.exists(name) && !replace {
+            return Err(anyhow::anyhow!(
+                "Profile '{}' already exists; import with replace to overwrite it",
+                name
+            ));
+        }
+
+        let mut config = config.clone();
+        (config.profile_cycle_hotkey, config.game_config) = if name == DEFAULT_PROFILE {
+            (
+                self.cycle_hotkey().unwrap_or_default(),
+                self.game_config().unwrap_or_default(),
+            )
+        } else {
+            (None, None)
+        };
+        self.save(name, &config)?;
+        info!("Imported profile '{}'", name);
+        Ok(())
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        if name == DEFAULT_PROFILE {
+            return Err(anyhow::anyhow!("The default profile can't be deleted"));
+        }
+        check_name(name)?;
+        if name == self.active() {
+            return Err(anyhow::anyhow!(
+                "Profile '{}' is active; switch to another profile first",
+                name
+            ));
+        }
+        let dependents = self.dependents(name)?;
+        if !dependents.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Profile '{}' is extended by {}; change or delete those first",
+                name,
+                dependents
+                    .iter()
+                    .map(|d| format!("'{}'", d))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        fs::remove_file(self.path_of(name))?;
+        info!("Deleted profile '{}'", name);
+        Ok(())
+    }
+
+    /// Make a profile active for this process only, e.g. under `--dry-run`
+    ///
+    /// Unlike [`ProfileStore::set_active`] the main config file keeps its choice.
+    pub fn select(&self, name: &str) {
+        *self.active.write().unwrap() = name.to_string();
+    }
+
+    /// Make a profile active and remember the choice in the main config file
+    pub fn set_active(&self, name: &str) -> Result<Config> {
+        check_profile(name)?;
+        let config = self.load(name)?;
+
+        *self.active.write().unwrap() = name.to_string();
+        let main = self.load(DEFAULT_PROFILE)?;
+        self.save(DEFAULT_PROFILE, &main)?;
+
+        info!("Switched to profile '{}'", name);
+        Ok(config)
+    }
+
+    /// Profile after the active one, wrapping around
+    pub fn next(&self) -> Result<String> {
+        let names = self.list()?;
+        let active = self.active();
+        let position = names.iter().position(|n| *n == active).unwrap_or(0);
+        Ok(names[(position + 1) % names.len()].clone())
+    }
+
+    /// Hotkey configured in the main config file for cycling profiles
+    pub fn cycle_hotkey(&self) -> Result<Option<String>> {
+        Ok(self
+            .read_main()?
+            .and_then(|config| config.profile_cycle_hotkey))
+    }
+
+    /// Game config file set in the main config file, if any
+    pub fn game_config(&self) -> Result<Option<String>> {
+        Ok(self.read_main()?.and_then(|config| config.game_config))
+    }
+
+    /// The main config file as it is on disk, if it exists yet
+    fn read_main(&self) -> Result<Option<Config>> {
+        if !self.exists(DEFAULT_PROFILE) {
+            return Ok(None);
+        }
+        self.read(DEFAULT_PROFILE).map(Some)
+    }
+
+    /// Warn about toggle hotkeys and keys that clash with the game's keybinds
+    pub fn check_game_keybinds(&self, config: &Config, report: &mut ValidationReport) {
+        game_keybinds::check(self.game_config_for(config).as_deref(), config, report);
+    }
+
+    /// `config` with game action targets resolved to the keys the game binds them to
+    pub fn resolve_game_actions(&self, config: &Config) -> Result<(Config, Vec<ResolvedAction>)> {
+        game_keybinds::resolve(self.game_config_for(config).as_deref(), config)
+    }
+
+    /// Game config file for `config`; its own `game_config` wins, for unsaved
+    /// edits of the main file
+    fn game_config_for(&self, config: &Config) -> Option<String> {
+        config
+            .game_config
+            .clone()
+            .or_else(|| self.game_config().ok().flatten())
+    }
+
+    fn active_pointer(&self) -> Option<String> {
+        let active = self.active();
+        (active != DEFAULT_PROFILE).then_some(active)
+    }
+}
+
+/// Profile names become file names, so keep them to a safe character set
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name != DEFAULT_PROFILE
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Invalid profile name '{}': use letters, digits, '-' and '_' (and not '{}')",
+            name,
+            DEFAULT_PROFILE
+        ))
+    }
+}
+
+/// [`check_name`] for a profile to use, where the default one is fine too
+fn check_profile(name: &str) -> Result<()> {
+    if name == DEFAULT_PROFILE {
+        return Ok(());
+    }
+    check_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_with(profiles: &[(&str, &str)]) -> (tempfile::TempDir, ProfileStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        fs::write(&config_path, "version: 2\nmacros: []\n").unwrap();
+        fs::create_dir(dir.path().join(PROFILES_DIR)).unwrap();
+        for (name, content) in profiles {
+            fs::write(
+                dir.path().join(PROFILES_DIR).join(format!("{}.yaml", name)),
+                content,
+            )
+            .unwrap();
+        }
+        let store = ProfileStore::open(&config_path);
+        (dir, store)
+    }
+
+    #[test]
+    fn delete_refuses_profiles_that_others_extend() {
+        let (_dir, store) = store_with(&[
+            ("base", "version: 2\nmacros: []\n"),
+            ("witch", "extends: base\n"),
+            ("ranger", "extends: base.yaml\n"),
+        ]);
+
+        assert_eq!(store.dependents("base").unwrap(), vec!["ranger", "witch"]);
+        let error = store.delete("base").unwrap_err().to_string();
+        assert!(error.contains("'ranger', 'witch'"), "{}", error);
+        assert!(store.exists("base"));
+
+        store.delete("witch").unwrap();
+        store.delete("ranger").unwrap();
+        store.delete("base").unwrap();
+        assert!(!store.exists("base"));
+    }
+
+    #[test]
+    fn names_cannot_reach_outside_the_profiles_directory() {
+        let (dir, store) = store_with(&[]);
+        let config_path = dir.path().join("config.yaml");
+
+        assert!(store.delete("../config").is_err());
+        assert!(config_path.exists());
+        assert!(store.load("../config").is_err());
+        assert!(store.read("../config").is_err());
+        assert!(store.set_active("../config").is_err());
+        assert_eq!(store.active(), DEFAULT_PROFILE);
+    }
+
+    #[test]
+    fn main_file_settings_are_read_without_writing() {
+        let (dir, store) = store_with(&[]);
+        let config_path = dir.path().join("config.yaml");
+        let v1 = "profile_cycle_hotkey: F8\ngame_config: poe2_production_Config.ini\nmacros:\n  - key: \"1\"\n    interval_ms: 1000\n    toggle_hotkey: F9\n";
+        fs::write(&config_path, v1).unwrap();
+
+        assert_eq!(store.cycle_hotkey().unwrap().as_deref(), Some("F8"));
+        assert_eq!(
+            store.game_config().unwrap().as_deref(),
+            Some("poe2_production_Config.ini")
+        );
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), v1);
+        assert!(storage::list_backups(&config_path).unwrap().is_empty());
+
+        fs::remove_file(&config_path).unwrap();
+        assert_eq!(store.cycle_hotkey().unwrap(), None);
+        assert!(!config_path.exists());
+    }
+
+    #[test]
+    fn read_only_loads_leave_old_and_missing_files_alone() {
+        let v1 = "macros:\n  - key: \"1\"\n    interval_ms: 1000\n    toggle_hotkey: F9\n";
+        let (dir, store) = store_with(&[("witch", v1)]);
+        let witch_path = store.path_of("witch");
+
+        let config = store.load_read_only("witch").unwrap();
+        assert_eq!(config.macros[0].key, "1");
+        assert_eq!(fs::read_to_string(&witch_path).unwrap(), v1);
+        assert!(storage::list_backups(&witch_path).unwrap().is_empty());
+
+        let config_path = dir.path().join("config.yaml");
+        fs::remove_file(&config_path).unwrap();
+        assert_eq!(
+            store.load_read_only(DEFAULT_PROFILE).unwrap(),
+            Config::default()
+        );
+        assert!(!config_path.exists());
+        assert!(store.load_read_only("ranger").is_err());
+    }
+}
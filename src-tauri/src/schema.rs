@@ -0,0 +1,197 @@
+//! JSON Schema of the config file format, for editor autocompletion
+
+use schemars::generate::SchemaSettings;
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::keys::{self, SUPPORTED_HOTKEYS};
+
+/// File name of the schema written next to a config by `init`
+pub const SCHEMA_FILE_NAME: &str = "config.schema.json";
+
+/// Definition of a complete macro, and of a macro entry in a profile that extends another
+const MACRO_DEFINITION: &str = "KeyMacro";
+const MACRO_OVERRIDE_DEFINITION: &str = "KeyMacroOverride";
+
+/// Comment that points the YAML language server (VS Code YAML extension) at a schema
+const HEADER_PREFIX: &str = "# yaml-language-server: $schema=";
+
+/// JSON Schema (draft-07) describing the config file as it may be hand-written
+pub fn config_schema() -> Value {
+    let mut schema = SchemaSettings::draft07()
+        .for_deserialize()
+        .into_generator()
+        .into_root_schema_for::<Config>();
+    schema.insert("title".to_string(), "POE2 Macro Buddy config".into());
+
+    let mut schema = schema.to_value();
+    if let Some(definitions) = schema.get_mut("definitions").and_then(Value::as_object_mut) {
+        if let Some(strict) = definitions.get(MACRO_DEFINITION) {
+            let relaxed = macro_override(strict.clone());
+            definitions.insert(MACRO_OVERRIDE_DEFINITION.to_string(), relaxed);
+        }
+    }
+    schema
+}
+
+/// Macro entries of a profile that extends another only change what they set
+fn macro_override(mut schema: Value) -> Value {
+    if let Some(object) = schema.as_object_mut() {
+        for strict in ["additionalProperties", "required", "oneOf"] {
+            object.remove(strict);
+        }
+        object.insert(
+            "description".to_string(),
+            "Override of an inherited macro with the same name, or a new macro".into(),
+        );
+        if let Some(properties) = object.get_mut("properties").and_then(Value::as_object_mut) {
+            properties.insert(
+                "remove".to_string(),
+                serde_json::json!({
+                    "description": "Drop the inherited macro with this name",
+                    "type": "boolean",
+                }),
+            );
+        }
+    }
+    schema
+}
+
+/// Pretty-printed schema, as written to disk or stdout
+pub fn config_schema_string() -> String {
+    serde_json::to_string_pretty(&config_schema()).unwrap_or_default()
+}
+
+/// The `# yaml-language-server: $schema=` line for `schema_ref`
+pub fn header(schema_ref: &str) -> String {
+    format!("{}{}\n", HEADER_PREFIX, schema_ref)
+}
+
+/// Schema header line at the top of `content`, if present
+pub fn header_of(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .next()
+        .filter(|line| line.starts_with(HEADER_PREFIX))
+}
+
+pub(crate) fn key_name(_: &mut SchemaGenerator) -> Schema {
+    // Same spellings the loader accepts, so editors don't flag `E` as unknown
+    let mut names = keys::key_spellings();
+    // Mouse macros leave the key empty
+    names.push(String::new());
+    json_schema!({
+        "type": "string",
+        "enum": names,
+    })
+}
+
+pub(crate) fn hotkey_name(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "string",
+        "enum": SUPPORTED_HOTKEYS,
+    })
+}
+
+pub(crate) fn optional_hotkey_name(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": ["string", "null"],
+        "enum": SUPPORTED_HOTKEYS.iter().map(|&k| Value::from(k)).chain([Value::Null]).collect::<Vec<_>>(),
+    })
+}
+
+/// Require exactly one of `interval_ms` and `interval` on a macro
+pub(crate) fn interval_alternatives(schema: &mut Schema) {
+    schema.insert(
+        "oneOf".to_string(),
+        serde_json::json!([
+            { "required": ["interval_ms"] },
+            { "required": ["interval"] },
+        ]),
+    );
+}
+
+/// Allow `extends` at the top level, which is read before the config itself
+///
+/// A config that extends another may leave out `macros`, and its macro
+/// entries are checked as overrides rather than complete macros.
+pub(crate) fn extends_property(schema: &mut Schema) {
+    let extends = crate::inheritance::EXTENDS_KEY;
+    if let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) {
+        properties.insert(
+            extends.to_string(),
+            serde_json::json!({
+                "description": "Profile name or file path this config builds on; its macros are overridden or removed by name",
+                "type": "string",
+            }),
+        );
+        if let Some(macros) = properties.get_mut("macros").and_then(Value::as_object_mut) {
+            macros.remove("items");
+        }
+    }
+    if let Some(required) = schema.get_mut("required").and_then(Value::as_array_mut) {
+        required.retain(|field| field != "macros");
+        if required.is_empty() {
+            schema.remove("required");
+        }
+    }
+
+    let items = |definition: &str| {
+        serde_json::json!({
+            "properties": {
+                "macros": { "items": { "$ref": format!("#/definitions/{}", definition) } },
+            },
+        })
+    };
+    let mut strict = items(MACRO_DEFINITION);
+    strict["required"] = serde_json::json!(["macros"]);
+    schema.insert(
+        "if".to_string(),
+        serde_json::json!({ "required": [extends] }),
+    );
+    schema.insert("then".to_string(), items(MACRO_OVERRIDE_DEFINITION));
+    schema.insert("else".to_string(), strict);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_valid(yaml: &str) -> bool {
+        let instance: Value = serde_yaml::from_str(yaml).unwrap();
+        jsonschema::is_valid(&config_schema(), &instance)
+    }
+
+    #[test]
+    fn complete_configs_need_complete_macros() {
+        assert!(is_valid(
+            "macros:\n  - key: \"1\"\n    interval_ms: 1000\n    toggle_hotkey: F9\n"
+        ));
+        assert!(!is_valid("version: 2\n"));
+        assert!(!is_valid(
+            "macros:\n  - name: flask\n    interval_ms: 900\n"
+        ));
+        assert!(!is_valid(
+            "macros:\n  - key: \"1\"\n    interval_ms: 1000\n    toggle_hotkey: F9\n    remove: true\n"
+        ));
+    }
+
+    #[test]
+    fn extending_configs_take_partial_macros() {
+        assert!(is_valid("extends: base\n"));
+        assert!(is_valid(
+            "extends: base\nmacros:\n  - name: flask\n    interval: 900ms\n  - name: skill\n    remove: true\n"
+        ));
+        assert!(is_valid(
+            "extends: base\nmacros:\n  - name: both\n    interval_ms: 900\n    interval: 1s\n"
+        ));
+        // Field values are still checked
+        assert!(!is_valid(
+            "extends: base\nmacros:\n  - name: flask\n    toggle_hotkey: F99\n"
+        ));
+        assert!(!is_valid(
+            "extends: base\nmacros:\n  - name: flask\n    remove: yes please\n"
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time;
+use tracing::{info, warn};
+
+use crate::config::SessionConfig;
+
+/// Snapshot of the current (or last) engine session
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct SessionStatus {
+    pub running: bool,
+    pub elapsed_secs: u64,
+    /// Seconds left before the engine stops itself, if a limit is configured
+    pub remaining_secs: Option<u64>,
+    /// Seconds until the next break reminder, if reminders are configured
+    pub next_break_secs: Option<u64>,
+    /// Whether the last session was ended by the runtime limit
+    pub limit_reached: bool,
+}
+
+/// Tracks runtime of a single engine session and enforces its limits
+pub struct SessionTimer {
+    config: SessionConfig,
+    started_at: Instant,
+    /// When the session ended, so its times stop moving afterwards
+    stopped_at: Mutex<Option<Instant>>,
+    limit_reached: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl SessionTimer {
+    pub fn start(config: SessionConfig) -> Self {
+        if let Some(minutes) = config.max_runtime_minutes {
+            info!("Session limit: engine stops after {} minute(s)", minutes);
+        }
+        if let Some(minutes) = config.break_reminder_minutes {
+            info!("Break reminders every {} minute(s)", minutes);
+        }
+
+        Self {
+            config,
+            started_at: Instant::now(),
+            stopped_at: Mutex::new(None),
+            limit_reached: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    /// Mark the session as ended so its tasks exit even if a new session starts
+    pub fn finish(&self) {
+        self.stopped_at
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+        self.finished.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the session ended; tasks of one run poll this to stop with it
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    /// Time the session ran, up to now or until it ended
+    pub fn elapsed(&self) -> Duration {
+        match *self.stopped_at.lock().unwrap() {
+            Some(stopped_at) => stopped_at.duration_since(self.started_at),
+            None => self.started_at.elapsed(),
+        }
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.config
+            .max_runtime_minutes
+            .map(|minutes| Duration::from_secs(minutes * 60).saturating_sub(self.elapsed()))
+    }
+
+    pub fn next_break(&self) -> Option<Duration> {
+        self.config.break_reminder_minutes.map(|minutes| {
+            let every = minutes * 60;
+            Duration::from_secs(every - self.elapsed().as_secs() % every)
+        })
+    }
+
+    pub fn status(&self, running: bool) -> SessionStatus {
+        SessionStatus {
+            running,
+            elapsed_secs: self.elapsed().as_secs(),
+            remaining_secs: self.remaining().map(|d| d.as_secs()),
+            next_break_secs: if running {
+                self.next_break().map(|d| d.as_secs())
+            } else {
+                None
+            },
+            limit_reached: self.limit_reached.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Watch the session until the engine stops, emitting break reminders
+    ///
+    /// Clears `running` and returns true when the runtime limit is reached.
+    pub async fn supervise(&self, running: &AtomicBool) -> bool {
+        let mut check_interval = time::interval(Duration::from_secs(1));
+        let mut reminders_sent = 0;
+
+        while running.load(Ordering::Relaxed) && !self.finished.load(Ordering::Relaxed) {
+            check_interval.tick().await;
+
+            if self.remaining() == Some(Duration::ZERO) {
+                self.limit_reached.store(true, Ordering::Relaxed);
+                self.finish();
+                running.store(false, Ordering::Relaxed);
+                warn!(
+                    "Session limit of {} minute(s) reached - stopping macro engine",
+                    self.config.max_runtime_minutes.unwrap_or_default()
+                );
+                return true;
+            }
+
+            if let Some(minutes) = self.config.break_reminder_minutes {
+                let due = self.elapsed().as_secs() / (minutes * 60);
+                if due > reminders_sent {
+                    reminders_sent = due;
+                    warn!(
+                        "Break reminder: macros have been running for {} minute(s) - consider taking a break",
+                        due * minutes
+                    );
+                }
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer(max_runtime_minutes: Option<u64>) -> SessionTimer {
+        SessionTimer::start(SessionConfig {
+            max_runtime_minutes,
+            break_reminder_minutes: None,
+        })
+    }
+
+    #[test]
+    fn times_freeze_once_finished() {
+        let session = timer(Some(10));
+        std::thread::sleep(Duration::from_millis(20));
+        session.finish();
+        let elapsed = session.elapsed();
+        let remaining = session.remaining();
+        assert!(elapsed >= Duration::from_millis(20));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(session.elapsed(), elapsed);
+        assert_eq!(session.remaining(), remaining);
+        assert!(session.is_finished());
+    }
+
+    #[test]
+    fn finishing_twice_keeps_the_first_stop() {
+        let session = timer(None);
+        session.finish();
+        let elapsed = session.elapsed();
+        std::thread::sleep(Duration::from_millis(20));
+        session.finish();
+        assert_eq!(session.elapsed(), elapsed);
+    }
+
+    #[test]
+    fn status_of_a_stopped_session_has_no_next_break() {
+        let session = SessionTimer::start(SessionConfig {
+            max_runtime_minutes: None,
+            break_reminder_minutes: Some(30),
+        });
+        assert_eq!(session.status(true).next_break_secs, Some(30 * 60));
+        session.finish();
+        let status = session.status(false);
+        assert!(!status.running);
+        assert_eq!(status.next_break_secs, None);
+    }
+}
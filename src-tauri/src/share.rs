@@ -0,0 +1,201 @@
+//! Compact text codes for sharing a profile, e.g. pasted into chat
+//!
+//! A code is `poe2mb:` followed by URL-safe base64 of a format version byte,
+//! the CRC32 of the profile YAML and the deflated YAML itself.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use serde::Serialize;
+use std::io::{Read, Write};
+
+use crate::config::Config;
+use crate::format::ConfigFormat;
+use crate::migration::MigrationReport;
+use crate::validation::ValidationReport;
+
+pub const CODE_PREFIX: &str = "poe2mb:";
+
+/// Layout of the decoded bytes; bump when it changes
+const FORMAT_VERSION: u8 = 1;
+
+/// Upper bound for the decompressed YAML, so a bad code can't exhaust memory
+const MAX_PROFILE_BYTES: u64 = 256 * 1024;
+
+/// A decoded profile code, upgraded and checked but not yet imported
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreview {
+    pub config: Config,
+    /// Schema upgrade applied because the code was made by an older version
+    pub migration: Option<MigrationReport>,
+    pub validation: ValidationReport,
+}
+
+/// Turn a profile into a share code
+///
+/// Settings that only apply to the main config file are left out.
+pub fn encode(config: &Config) -> Result<String> {
+    let mut config = config.without_derived_ids();
+    config.active_profile = None;
+    config.profile_cycle_hotkey = None;
+    config.game_config = None;
+    let yaml = serde_yaml::to_string(&config)?;
+
+    let mut crc = Crc::new();
+    crc.update(yaml.as_bytes());
+
+    let mut bytes = vec![FORMAT_VERSION];
+    bytes.extend_from_slice(&crc.sum().to_be_bytes());
+    let mut encoder = DeflateEncoder::new(bytes, Compression::best());
+    encoder.write_all(yaml.as_bytes())?;
+    let bytes = encoder.finish()?;
+
+    Ok(format!("{}{}", CODE_PREFIX, URL_SAFE_NO_PAD.encode(bytes)))
+}
+
+/// Decode a share code, migrating and validating the profile it holds
+///
+/// Whitespace is ignored, since chat clients like to wrap long lines.
+pub fn decode(code: &str) -> Result<ImportPreview> {
+    let code: String = code.split_whitespace().collect();
+    let payload = code.strip_prefix(CODE_PREFIX).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Not a profile code (expected it to start with '{}')",
+            CODE_PREFIX
+        )
+    })?;
+    let bytes = URL_SAFE_NO_PAD
+        .decode(payload)
+        .context("Profile code is damaged (invalid characters)")?;
+
+    let (&version, rest) = bytes
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("Profile code is empty"))?;
+    if version != FORMAT_VERSION {
+        return Err(anyhow::anyhow!(
+            "Profile code format {} is not supported by this version (expected {}); update the app",
+            version,
+            FORMAT_VERSION
+        ));
+    }
+    if rest.len() < 4 {
+        return Err(anyhow::anyhow!("Profile code is truncated"));
+    }
+    let (checksum, compressed) = rest.split_at(4);
+
+    let mut yaml = String::new();
+    DeflateDecoder::new(compressed)
+        .take(MAX_PROFILE_BYTES + 1)
+        .read_to_string(&mut yaml)
+        .context("Profile code is damaged (could not decompress it)")?;
+    if yaml.len() as u64 > MAX_PROFILE_BYTES {
+        return Err(anyhow::anyhow!("Profile code is too large"));
+    }
+
+    let mut crc = Crc::new();
+    crc.update(yaml.as_bytes());
+    if crc.sum().to_be_bytes() != checksum {
+        return Err(anyhow::anyhow!(
+            "Profile code is damaged (checksum mismatch); copy it again"
+        ));
+    }
+
+    let (config, migration) =
+        Config::parse(&yaml, ConfigFormat::Yaml).context("Profile code holds an invalid config")?;
+    let validation = config.validate_report();
+    Ok(ImportPreview {
+        config,
+        migration,
+        validation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A code built by hand, to make the ones `encode` never would
+    fn raw_code(version: u8, yaml: &str) -> String {
+        let mut crc = Crc::new();
+        crc.update(yaml.as_bytes());
+        let mut bytes = vec![version];
+        bytes.extend_from_slice(&crc.sum().to_be_bytes());
+        let mut encoder = DeflateEncoder::new(bytes, Compression::best());
+        encoder.write_all(yaml.as_bytes()).unwrap();
+        format!(
+            "{}{}",
+            CODE_PREFIX,
+            URL_SAFE_NO_PAD.encode(encoder.finish().unwrap())
+        )
+    }
+
+    fn error_of(code: &str) -> String {
+        decode(code).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn codes_round_trip_without_main_file_settings() {
+        let mut config = Config {
+            profile_cycle_hotkey: Some("F8".to_string()),
+            game_config: Some("poe2_production_Config.ini".to_string()),
+            ..Config::default()
+        };
+        let code = encode(&config).unwrap();
+        assert!(code.starts_with(CODE_PREFIX));
+
+        // Chat clients wrap long codes
+        let (head, tail) = code.split_at(code.len() / 2);
+        let preview = decode(&format!("{}\n  {}", head, tail)).unwrap();
+        config.profile_cycle_hotkey = None;
+        config.game_config = None;
+        assert_eq!(preview.config, config);
+        assert!(preview.migration.is_none());
+        assert!(preview.validation.issues.is_empty());
+    }
+
+    #[test]
+    fn codes_need_the_prefix() {
+        let code = encode(&Config::default()).unwrap();
+        let error = error_of(code.strip_prefix(CODE_PREFIX).unwrap());
+        assert!(error.starts_with("Not a profile code"), "{}", error);
+    }
+
+    #[test]
+    fn a_changed_character_fails_the_checksum() {
+        let code = encode(&Config::default()).unwrap();
+        // The third character carries bits of the checksum
+        let at = CODE_PREFIX.len() + 2;
+        let flipped = if &code[at..=at] == "A" { "B" } else { "A" };
+        let damaged = format!("{}{}{}", &code[..at], flipped, &code[at + 1..]);
+        assert!(error_of(&damaged).contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn truncated_codes_fail() {
+        let code = encode(&Config::default()).unwrap();
+        assert_eq!(error_of(CODE_PREFIX), "Profile code is empty");
+        assert_eq!(
+            error_of(&code[..CODE_PREFIX.len() + 4]),
+            "Profile code is truncated"
+        );
+        assert!(error_of(&code[..code.len() / 2]).contains("damaged"));
+    }
+
+    #[test]
+    fn unknown_format_versions_fail() {
+        let error = error_of(&raw_code(FORMAT_VERSION + 1, "macros: []\n"));
+        assert!(error.contains("format 2 is not supported"), "{}", error);
+    }
+
+    #[test]
+    fn oversized_profiles_fail() {
+        let yaml = format!("#{}\nmacros: []\n", "x".repeat(MAX_PROFILE_BYTES as usize));
+        assert_eq!(
+            error_of(&raw_code(FORMAT_VERSION, &yaml)),
+            "Profile code is too large"
+        );
+    }
+}
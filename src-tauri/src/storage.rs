@@ -0,0 +1,132 @@
+//! Crash-safe config file writes with rotating backups
+//!
+//! Files are written to a temporary sibling and renamed over the original,
+//! so a crash mid-write never leaves a truncated config behind. The version
+//! being replaced is kept in a `backups/` directory next to the file.
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use flate2::Crc;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const BACKUP_DIR: &str = "backups";
+
+/// Number of backups kept per file
+pub const MAX_BACKUPS: usize = 5;
+
+/// What a file on disk looked like at some point, to notice later changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileVersion {
+    /// CRC-32 of the contents
+    pub hash: u32,
+    pub modified: Option<DateTime<Local>>,
+}
+
+impl FileVersion {
+    /// Current version of `path`, or `None` when it can't be read
+    pub fn of(path: &Path) -> Option<Self> {
+        let contents = fs::read(path).ok()?;
+        let mut crc = Crc::new();
+        crc.update(&contents);
+        let modified = fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .map(DateTime::<Local>::from);
+        Some(Self {
+            hash: crc.sum(),
+            modified,
+        })
+    }
+}
+
+/// Replace `path` with `contents`, keeping the previous version as a backup
+pub fn write_with_backup(path: &Path, contents: &str) -> Result<()> {
+    if let Ok(previous) = fs::read_to_string(path) {
+        if previous != contents {
+            backup(path, &previous)?;
+        }
+    }
+    atomic_write(path, contents)
+}
+
+/// Write `contents` to a temporary file and atomically rename it over `path`
+pub fn atomic_write(path: &Path, contents: &str) -> Result<()> {
+    let tmp = sibling(path, "tmp");
+
+    let result = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(anyhow::anyhow!("Failed to write {}: {}", path.display(), e));
+    }
+    Ok(())
+}
+
+/// Backups of `path`, newest first
+pub fn list_backups(path: &Path) -> Result<Vec<PathBuf>> {
+    let prefix = format!("{}.", file_name(path));
+    let mut backups = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(backup_dir(path)) {
+        for entry in entries {
+            let backup = entry?.path();
+            let name = backup.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if name.starts_with(&prefix) && name.ends_with(".bak") {
+                backups.push(backup);
+            }
+        }
+    }
+
+    // Timestamps sort lexically, so the newest backup has the largest name
+    backups.sort();
+    backups.reverse();
+    Ok(backups)
+}
+
+/// Move a corrupt file aside so it is kept for inspection but no longer loaded
+pub fn quarantine(path: &Path) -> Result<PathBuf> {
+    let target = sibling(path, &format!("corrupt-{}", timestamp()));
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+fn backup(path: &Path, contents: &str) -> Result<()> {
+    let dir = backup_dir(path);
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        dir.join(format!("{}.{}.bak", file_name(path), timestamp())),
+        contents,
+    )?;
+
+    for old in list_backups(path)?.into_iter().skip(MAX_BACKUPS) {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+fn backup_dir(path: &Path) -> PathBuf {
+    path.parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(BACKUP_DIR)
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    path.with_file_name(format!("{}.{}", file_name(path), suffix))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn timestamp() -> String {
+    Local::now().format("%Y%m%d-%H%M%S%.3f").to_string()
+}
@@ -0,0 +1,132 @@
+//! Ready-made configs compiled into the binary, as starting points
+//!
+//! Each template is a commented YAML file in `templates/`. New configs and
+//! profiles made from a YAML template keep its comments; other formats get the
+//! same settings without them.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::format::ConfigFormat;
+
+/// Template used for a new config when none is chosen
+pub const DEFAULT_TEMPLATE: &str = "starter";
+
+/// A named example config
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Template {
+    /// Name used to pick the template, e.g. `flask-rotation`
+    pub name: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    /// The template file itself, comments included
+    pub content: &'static str,
+}
+
+/// A template together with the config it produces
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplatePreview {
+    pub template: Template,
+    pub config: Config,
+}
+
+const TEMPLATES: &[Template] = &[
+    Template {
+        name: "starter",
+        title: "Starter",
+        description: "One key, one skill and a left click on F9-F11",
+        content: include_str!("../templates/starter.yaml"),
+    },
+    Template {
+        name: "flask-rotation",
+        title: "Flask rotation",
+        description: "Life and mana flasks on 1 and 2, offset so they rarely fire together",
+        content: include_str!("../templates/flask-rotation.yaml"),
+    },
+    Template {
+        name: "buff-refresh",
+        title: "Buff refresh",
+        description: "Recast timed buffs on w and r shortly before they expire",
+        content: include_str!("../templates/buff-refresh.yaml"),
+    },
+    Template {
+        name: "channel-helper",
+        title: "Channel helper",
+        description: "Repeat a single skill on q at a steady pace",
+        content: include_str!("../templates/channel-helper.yaml"),
+    },
+    Template {
+        name: "click-helper",
+        title: "Click helper",
+        description: "A steady left click for the default attack",
+        content: include_str!("../templates/click-helper.yaml"),
+    },
+];
+
+/// Every built-in template, the default first
+pub fn all() -> &'static [Template] {
+    TEMPLATES
+}
+
+/// Template by name
+pub fn find(name: &str) -> Result<&'static Template> {
+    TEMPLATES.iter().find(|t| t.name == name).ok_or_else(|| {
+        let names: Vec<&str> = TEMPLATES.iter().map(|t| t.name).collect();
+        anyhow::anyhow!(
+            "Unknown template '{}' (available: {})",
+            name,
+            names.join(", ")
+        )
+    })
+}
+
+impl Template {
+    /// The config this template describes
+    pub fn config(&self) -> Result<Config> {
+        Config::parse(self.content, ConfigFormat::Yaml)
+            .map(|(config, _)| config)
+            .map_err(|e| anyhow::anyhow!("Template '{}' is invalid: {}", self.name, e))
+    }
+
+    /// File content for a config in `format`, keeping the comments in YAML
+    pub fn render(&self, format: ConfigFormat) -> Result<String> {
+        match format {
+            ConfigFormat::Yaml => Ok(self.content.to_string()),
+            _ => Config::convert(self.content, ConfigFormat::Yaml, format),
+        }
+    }
+
+    pub fn preview(&self) -> Result<TemplatePreview> {
+        Ok(TemplatePreview {
+            template: *self,
+            config: self.config()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_template_is_a_valid_current_config() {
+        assert_eq!(all()[0].name, DEFAULT_TEMPLATE);
+        assert_eq!(Config::default(), all()[0].config().unwrap());
+
+        for template in all() {
+            let (config, migration) = Config::parse(template.content, ConfigFormat::Yaml)
+                .unwrap_or_else(|e| panic!("{}: {}", template.name, e));
+            assert!(migration.is_none(), "{} needs a migration", template.name);
+            assert!(!config.macros.is_empty(), "{} has no macros", template.name);
+            let report = config.validate_report();
+            assert!(!report.has_errors(), "{}: {:?}", template.name, report);
+
+            for format in ConfigFormat::ALL {
+                let rendered = template.render(format).unwrap();
+                let (read_back, _) = Config::parse(&rendered, format).unwrap();
+                assert_eq!(read_back, config, "{} as {:?}", template.name, format);
+            }
+        }
+    }
+}
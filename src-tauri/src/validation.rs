@@ -0,0 +1,102 @@
+use serde::Serialize;
+use std::fmt;
+
+/// How serious a validation issue is
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The config can't be used until this is fixed
+    Error,
+    /// The config works, but probably not the way the user intended
+    Warning,
+}
+
+/// A single problem found in a configuration
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    /// Field path of the offending value, e.g. `macros[2].toggle_hotkey`
+    pub path: String,
+    pub severity: Severity,
+    /// Stable machine-readable identifier, e.g. `duplicate_hotkey`
+    pub code: &'static str,
+    pub message: String,
+    /// File and profile the value came from, for profiles that extend others
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)?;
+        if let Some(source) = &self.source {
+            write!(f, " (from {})", source)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every problem found in a configuration, collected in one pass
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn error(
+        &mut self,
+        path: impl Into<String>,
+        code: &'static str,
+        message: impl Into<String>,
+    ) {
+        self.push(path.into(), Severity::Error, code, message.into());
+    }
+
+    pub fn warning(
+        &mut self,
+        path: impl Into<String>,
+        code: &'static str,
+        message: impl Into<String>,
+    ) {
+        self.push(path.into(), Severity::Warning, code, message.into());
+    }
+
+    fn push(&mut self, path: String, severity: Severity, code: &'static str, message: String) {
+        self.issues.push(ValidationIssue {
+            path,
+            severity,
+            code,
+            message,
+            source: None,
+        });
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// Fail with every error in the report; warnings are ignored
+    pub fn into_result(self) -> anyhow::Result<()> {
+        if !self.has_errors() {
+            return Ok(());
+        }
+
+        let messages: Vec<String> = self.errors().map(|issue| issue.to_string()).collect();
+        Err(anyhow::anyhow!(messages.join("; ")))
+    }
+}
@@ -0,0 +1,247 @@
+use anyhow::Result;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::macro_engine::MacroEngineState;
+
+/// Quiet period after the last file event before a reload, so editors that
+/// write in several steps only trigger one reload
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watches the active profile's config file and hot-reloads it
+///
+/// Valid changes are applied to the running engine without a restart; invalid
+/// ones are logged and the previous config keeps running. The watcher stops
+/// when this value is dropped.
+pub struct ConfigWatcher {
+    _watcher: Arc<Mutex<RecommendedWatcher>>,
+}
+
+impl ConfigWatcher {
+    /// Start watching; `on_reload` is called with every valid reloaded config
+    pub fn start<F>(engine: MacroEngineState, on_reload: F) -> Result<Self>
+    where
+        F: Fn(Config) + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })?;
+
+        // Watch directories rather than files: atomic saves replace the file
+        let profiles = engine.profiles().clone();
+        let config_dir = parent_dir(&profiles.path_of(crate::profiles::DEFAULT_PROFILE));
+        watcher.watch(&config_dir, RecursiveMode::NonRecursive)?;
+        let profiles_dir = profiles.profiles_dir();
+        let mut watching_profiles = profiles_dir.is_dir();
+        if watching_profiles {
+            watcher.watch(&profiles_dir, RecursiveMode::NonRecursive)?;
+        }
+        info!("Watching {} for config changes", config_dir.display());
+
+        // The task only borrows the watcher, so dropping this value still stops it
+        let watcher = Arc::new(Mutex::new(watcher));
+        let weak_watcher = Arc::downgrade(&watcher);
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                // `profiles/` only appears once the first profile is created
+                if !watching_profiles && profiles_dir.is_dir() {
+                    let Some(watcher) = weak_watcher.upgrade() else {
+                        break;
+                    };
+                    let result = watcher
+                        .lock()
+                        .unwrap()
+                        .watch(&profiles_dir, RecursiveMode::NonRecursive);
+                    match result {
+                        Ok(()) => {
+                            watching_profiles = true;
+                            info!("Watching {} for profile changes", profiles_dir.display());
+                        }
+                        Err(e) => warn!("Cannot watch {}: {}", profiles_dir.display(), e),
+                    }
+                }
+
+                // A change to anything the active profile extends counts too
+                let active = profiles.active();
+                let path = profiles.active_path();
+                if !profiles
+                    .layer_paths(&active)
+                    .iter()
+                    .any(|layer| is_change_to(&event, layer))
+                {
+                    continue;
+                }
+
+                // Wait until the file has been quiet for a moment
+                while let Ok(Some(_)) = tokio::time::timeout(DEBOUNCE, rx.recv()).await {}
+
+                let config = match profiles.read(&active) {
+                    Ok(config) => config,
+                    Err(e) => {
+                        warn!(
+                            "Ignoring change to {}: {} (keeping the previous config)",
+                            path.display(),
+                            e
+                        );
+                        continue;
+                    }
+                };
+                let mut report = config.validate_report();
+                profiles.annotate(&config, &mut report);
+                if let Err(e) = report.into_result() {
+                    warn!(
+                        "Ignoring invalid change to {}: {} (keeping the previous config)",
+                        path.display(),
+                        e
+                    );
+                    continue;
+                }
+
+                if engine.is_running() {
+                    match engine.apply_config(config.clone()).await {
+                        Ok(summary) if summary.is_empty() => {}
+                        Ok(summary) => info!(
+                            "Reloaded {}: {} added, {} updated, {} removed",
+                            path.display(),
+                            summary.added.len(),
+                            summary.updated.len(),
+                            summary.removed.len()
+                        ),
+                        Err(e) => {
+                            warn!("Could not apply {}: {}", path.display(), e);
+                            continue;
+                        }
+                    }
+                }
+                on_reload(config);
+            }
+        });
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Whether a watcher event modified the file at `path`
+fn is_change_to(event: &notify::Result<notify::Event>, path: &Path) -> bool {
+    let Ok(event) = event else {
+        return false;
+    };
+    if !matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any
+    ) {
+        return false;
+    }
+    let target = canonical(path);
+    event.paths.iter().any(|p| canonical(p) == target)
+}
+
+/// Canonical form of a path whose file may be briefly missing mid-save
+fn canonical(path: &Path) -> PathBuf {
+    let dir = parent_dir(path);
+    let dir = dir.canonicalize().unwrap_or(dir);
+    match path.file_name() {
+        Some(name) => dir.join(name),
+        None => dir,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overrides::Overrides;
+    use crate::profiles::ProfileStore;
+    use notify::event::{AccessKind, CreateKind, ModifyKind, RemoveKind};
+    use std::fs;
+
+    fn event(kind: EventKind, path: &Path) -> notify::Result<notify::Event> {
+        Ok(notify::Event::new(kind).add_path(path.to_path_buf()))
+    }
+
+    #[test]
+    fn only_writes_to_the_watched_file_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        let modify = EventKind::Modify(ModifyKind::Any);
+
+        assert!(is_change_to(&event(modify, &path), &path));
+        assert!(is_change_to(
+            &event(EventKind::Create(CreateKind::File), &path),
+            &path
+        ));
+        // The same file reached another way, even while it is missing mid-save
+        let dotted = dir.path().join(".").join("config.yaml");
+        assert!(is_change_to(&event(modify, &dotted), &path));
+
+        assert!(!is_change_to(
+            &event(modify, &dir.path().join("other.yaml")),
+            &path
+        ));
+        assert!(!is_change_to(
+            &event(EventKind::Access(AccessKind::Any), &path),
+            &path
+        ));
+        assert!(!is_change_to(
+            &event(EventKind::Remove(RemoveKind::File), &path),
+            &path
+        ));
+        assert!(!is_change_to(
+            &Err(notify::Error::generic("watch failed")),
+            &path
+        ));
+    }
+
+    #[tokio::test]
+    async fn profiles_created_after_starting_are_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        let macros = |key: &str| {
+            format!(
+                "version: 2\nmacros:\n  - key: '{}'\n    interval_ms: 1000\n    toggle_hotkey: F9\n",
+                key
+            )
+        };
+        fs::write(&config_path, macros("1")).unwrap();
+        let profiles = ProfileStore::open(&config_path);
+        let engine = MacroEngineState::new(profiles.clone(), Overrides::default());
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let _watcher = ConfigWatcher::start(engine, move |config| {
+            let _ = tx.send(config);
+        })
+        .unwrap();
+
+        // Creating the first profile makes `profiles/`
+        profiles.create("witch", &Config::default()).unwrap();
+        profiles.select("witch");
+        let witch = profiles.path_of("witch");
+        let reloaded = async {
+            loop {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                fs::write(&witch, macros("2")).unwrap();
+                let wait = tokio::time::timeout(DEBOUNCE * 3, rx.recv()).await;
+                if let Ok(Some(config)) = wait {
+                    if config.macros[0].key == "2" {
+                        return;
+                    }
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), reloaded)
+            .await
+            .expect("edits to the new profile were not picked up");
+    }
+}
@@ -0,0 +1,701 @@
+//! In-place editing of block-style YAML documents
+//!
+//! Saves go through [`patch`], which rewrites only the lines whose values
+//! changed so comments, field order and unknown fields survive. Nodes that
+//! cannot be patched line by line are re-serialized on their own; when even
+//! that is not possible the caller falls back to a full rewrite.
+
+use serde_yaml::{Mapping, Value};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Update `text` so it holds `new` instead of `old`
+///
+/// `old` must be what `text` deserializes to, with paths matching the text.
+/// Every candidate is checked with `verify`; returns `None` when the document
+/// cannot be edited in place.
+pub fn patch(
+    text: &str,
+    old: &Value,
+    new: &Value,
+    verify: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let nodes = Locator::new(&lines).locate()?;
+
+    // A value missing from the text was either defaulted, so adding the key is
+    // right, or written under another name, so its parent must be rewritten
+    [true, false].into_iter().find_map(|insert_missing| {
+        let editor = Editor {
+            lines: &lines,
+            nodes: &nodes,
+            insert_missing,
+        };
+        let edits = editor.diff("", old, new)?;
+        Some(apply(&lines, edits)).filter(|patched| verify(patched))
+    })
+}
+
+fn apply(lines: &[&str], edits: Vec<Edit>) -> String {
+    let mut output: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+
+    // Apply bottom-up; ties keep generation order (e.g. a key added to the
+    // last macro lands before a macro appended after it)
+    let mut edits: Vec<Edit> = edits.into_iter().rev().collect();
+    edits.sort_by_key(|edit| Reverse(edit.start));
+    for edit in edits {
+        output.splice(edit.start..edit.end, edit.lines);
+    }
+
+    let mut result = output.join("\n");
+    result.push('\n');
+    result
+}
+
+/// Where a node sits in the text
+#[derive(Debug)]
+struct Node {
+    /// First line of the node (its key or `-` line)
+    start: usize,
+    /// One past the last line holding the node or its children
+    end: usize,
+    /// Column where the node's key or `-` begins
+    indent: usize,
+    /// Whether this is a mapping entry (as opposed to a sequence item or the root)
+    is_entry: bool,
+    /// Line and columns of an inline scalar value
+    scalar: Option<(usize, Range<usize>)>,
+    /// Child indentation if the value is a block mapping or sequence
+    container: Option<Container>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Mapping(usize),
+    Sequence(usize),
+}
+
+/// A significant piece of a line: `- `, `key:` (with optional value) or a scalar
+#[derive(Debug)]
+struct Token {
+    line: usize,
+    col: usize,
+    kind: TokenKind,
+}
+
+#[derive(Debug)]
+enum TokenKind {
+    Dash,
+    Key {
+        key: String,
+        value: Option<Range<usize>>,
+    },
+    Scalar(Range<usize>),
+}
+
+struct Locator {
+    tokens: Vec<Token>,
+    pos: usize,
+    nodes: HashMap<String, Node>,
+}
+
+impl Locator {
+    fn new(lines: &[&str]) -> Self {
+        let mut tokens = Vec::new();
+        for (idx, line) in lines.iter().enumerate() {
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            let content = line[indent..].trim_end();
+            if content.is_empty()
+                || content.starts_with('#')
+                || content == "---"
+                || content.starts_with('%')
+            {
+                continue;
+            }
+            tokenize(line, idx, indent, &mut tokens);
+        }
+        Self {
+            tokens,
+            pos: 0,
+            nodes: HashMap::new(),
+        }
+    }
+
+    fn locate(mut self) -> Option<HashMap<String, Node>> {
+        let first = self.tokens.first()?;
+        let (start, indent) = (first.line, first.col);
+        if !matches!(first.kind, TokenKind::Key { .. }) {
+            return None;
+        }
+        let end = self.mapping("", indent)?;
+        if self.pos != self.tokens.len() {
+            return None;
+        }
+        self.nodes.insert(
+            String::new(),
+            Node {
+                start,
+                end,
+                indent,
+                is_entry: false,
+                scalar: None,
+                container: Some(Container::Mapping(indent)),
+            },
+        );
+        Some(self.nodes)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Parse mapping entries at `indent`; returns the end line
+    fn mapping(&mut self, path: &str, indent: usize) -> Option<usize> {
+        let mut end = 0;
+        while let Some(token) = self.peek() {
+            let TokenKind::Key { key, value } = &token.kind else {
+                break;
+            };
+            if token.col != indent {
+                break;
+            }
+            let (line, key, value) = (token.line, key.clone(), value.clone());
+            self.pos += 1;
+
+            let child_path = join(path, &key);
+            let mut node = Node {
+                start: line,
+                end: line + 1,
+                indent,
+                is_entry: true,
+                scalar: value.clone().map(|range| (line, range)),
+                container: None,
+            };
+
+            let next = self
+                .peek()
+                .map(|next| (next.col, matches!(next.kind, TokenKind::Dash)));
+            match next {
+                // Multi-line scalar after an inline value: leave opaque
+                Some((col, _)) if value.is_some() && col > indent => {
+                    node.scalar = None;
+                    node.end = self.skip_deeper(indent);
+                }
+                // Block sequences may sit at the same indentation as their key
+                Some((col, is_dash))
+                    if value.is_none() && (col > indent || (col == indent && is_dash)) =>
+                {
+                    let (container, child_end) = self.block(&child_path, col)?;
+                    node.container = Some(container);
+                    node.end = child_end;
+                }
+                _ => {}
+            }
+
+            end = node.end;
+            self.nodes.insert(child_path, node);
+        }
+        Some(end)
+    }
+
+    /// Parse sequence items at `indent`; returns the end line
+    fn sequence(&mut self, path: &str, indent: usize) -> Option<usize> {
+        let mut end = 0;
+        let mut index = 0;
+        while let Some(token) = self.peek() {
+            if !matches!(token.kind, TokenKind::Dash) || token.col != indent {
+                break;
+            }
+            let line = token.line;
+            self.pos += 1;
+
+            let child_path = format!("{}[{}]", path, index);
+            let mut node = Node {
+                start: line,
+                end: line + 1,
+                indent,
+                is_entry: false,
+                scalar: None,
+                container: None,
+            };
+
+            let next = self.peek().map(|next| match &next.kind {
+                TokenKind::Scalar(range) if next.line == line => (next.col, Some(range.clone())),
+                _ => (next.col, None),
+            });
+            match next {
+                Some((_, Some(range))) => {
+                    node.scalar = Some((line, range));
+                    self.pos += 1;
+                    // Continuation lines of a multi-line scalar: leave opaque
+                    if self.peek().is_some_and(|next| next.col > indent) {
+                        node.scalar = None;
+                        node.end = self.skip_deeper(indent);
+                    }
+                }
+                Some((col, None)) if col > indent => {
+                    let (container, child_end) = self.block(&child_path, col)?;
+                    node.container = Some(container);
+                    node.end = child_end;
+                }
+                _ => {}
+            }
+
+            end = node.end;
+            self.nodes.insert(child_path, node);
+            index += 1;
+        }
+        Some(end)
+    }
+
+    fn block(&mut self, path: &str, indent: usize) -> Option<(Container, usize)> {
+        match self.peek()?.kind {
+            TokenKind::Dash => Some((Container::Sequence(indent), self.sequence(path, indent)?)),
+            TokenKind::Key { .. } => {
+                Some((Container::Mapping(indent), self.mapping(path, indent)?))
+            }
+            TokenKind::Scalar(_) => None,
+        }
+    }
+
+    /// Skip every token indented deeper than `indent`; returns the end line
+    fn skip_deeper(&mut self, indent: usize) -> usize {
+        let mut end = 0;
+        while let Some(token) = self.peek() {
+            if token.col <= indent {
+                break;
+            }
+            end = token.line + 1;
+            self.pos += 1;
+        }
+        end
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Split the content of one line starting at `col` into tokens
+fn tokenize(line: &str, idx: usize, col: usize, tokens: &mut Vec<Token>) {
+    let content = &line[col..];
+    if content == "-" || content.starts_with("- ") {
+        tokens.push(Token {
+            line: idx,
+            col,
+            kind: TokenKind::Dash,
+        });
+        let rest = &content[1..];
+        let skip = rest.len() - rest.trim_start().len();
+        let next = col + 1 + skip;
+        if next < line.len()
+            && !line[next..].trim_end().is_empty()
+            && !line[next..].starts_with('#')
+        {
+            tokenize(line, idx, next, tokens);
+        }
+        return;
+    }
+
+    let value_end = col + value_length(content);
+    let kind = match key_split(content) {
+        Some(colon) if !content.starts_with('{') && !content.starts_with('[') => {
+            let key = unquote(content[..colon].trim());
+            let after = col + colon + 1;
+            let skip = line[after..].len() - line[after..].trim_start().len();
+            let start = after + skip;
+            let value = (start < value_end).then_some(start..value_end);
+            TokenKind::Key { key, value }
+        }
+        _ => TokenKind::Scalar(col..value_end),
+    };
+    tokens.push(Token {
+        line: idx,
+        col,
+        kind,
+    });
+}
+
+/// Position of the `:` ending a mapping key, outside quotes
+fn key_split(content: &str) -> Option<usize> {
+    let bytes = content.as_bytes();
+    let mut quote = None;
+    for (i, &b) in bytes.iter().enumerate() {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if (b == b'\'' || b == b'"') && i == 0 => quote = Some(b),
+            None if b == b'#' && i > 0 && bytes[i - 1] == b' ' => return None,
+            None if b == b':' && (i + 1 == bytes.len() || bytes[i + 1] == b' ') => return Some(i),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Length of `content` without a trailing comment and whitespace
+fn value_length(content: &str) -> usize {
+    let bytes = content.as_bytes();
+    let mut quote = None;
+    let mut end = bytes.len();
+    for (i, &b) in bytes.iter().enumerate() {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if b == b'\'' || b == b'"' => quote = Some(b),
+            None if b == b'#' && (i == 0 || bytes[i - 1] == b' ' || bytes[i - 1] == b'\t') => {
+                end = i;
+                break;
+            }
+            None => {}
+        }
+    }
+    content[..end].trim_end().len()
+}
+
+fn unquote(key: &str) -> String {
+    let quoted = key.len() >= 2
+        && ((key.starts_with('\'') && key.ends_with('\''))
+            || (key.starts_with('"') && key.ends_with('"')));
+    if quoted {
+        key[1..key.len() - 1].to_string()
+    } else {
+        key.to_string()
+    }
+}
+
+/// Replace lines `start..end` with `lines`
+#[derive(Debug)]
+struct Edit {
+    start: usize,
+    end: usize,
+    lines: Vec<String>,
+}
+
+struct Editor<'a> {
+    lines: &'a [&'a str],
+    nodes: &'a HashMap<String, Node>,
+    /// Add keys whose old value is not in the text instead of rewriting the parent
+    insert_missing: bool,
+}
+
+impl Editor<'_> {
+    /// Edits turning `old` into `new` at `path`, rewriting the node if needed
+    fn diff(&self, path: &str, old: &Value, new: &Value) -> Option<Vec<Edit>> {
+        if old == new {
+            return Some(Vec::new());
+        }
+        self.patch(path, old, new)
+            .or_else(|| self.rewrite(path, new))
+    }
+
+    fn patch(&self, path: &str, old: &Value, new: &Value) -> Option<Vec<Edit>> {
+        let node = self.nodes.get(path)?;
+        match (old, new) {
+            (Value::Mapping(old), Value::Mapping(new)) => {
+                let Some(Container::Mapping(indent)) = node.container else {
+                    return None;
+                };
+                self.patch_mapping(path, node, indent, old, new)
+            }
+            (Value::Sequence(old), Value::Sequence(new)) => {
+                let Some(Container::Sequence(indent)) = node.container else {
+                    return None;
+                };
+                self.patch_sequence(path, node, indent, old, new)
+            }
+            (old, new) if is_scalar(old) && is_scalar(new) => {
+                let (line, mut range) = node.scalar.clone()?;
+                let value = render_scalar(new)?;
+                let text = self.lines[line];
+                // Keep an anchor so aliases of the value follow it
+                if text[range.clone()].starts_with('&') {
+                    let anchor = text[range.clone()].find(' ')?;
+                    range.start += anchor + 1;
+                }
+                Some(vec![Edit {
+                    start: line,
+                    end: line + 1,
+                    lines: vec![format!(
+                        "{}{}{}",
+                        &text[..range.start],
+                        value,
+                        &text[range.end..]
+                    )],
+                }])
+            }
+            _ => None,
+        }
+    }
+
+    fn patch_mapping(
+        &self,
+        path: &str,
+        node: &Node,
+        indent: usize,
+        old: &Mapping,
+        new: &Mapping,
+    ) -> Option<Vec<Edit>> {
+        let mut edits = Vec::new();
+        for (key, value) in new {
+            if old.get(key) == Some(value) {
+                continue;
+            }
+            let child_path = join(path, key.as_str()?);
+            match (old.get(key), self.nodes.contains_key(&child_path)) {
+                (Some(previous), true) => edits.extend(self.diff(&child_path, previous, value)?),
+                (Some(_), false) if !self.insert_missing => return None,
+                // New key, or one that only had its default value and is not in the text
+                _ => {
+                    let mut entry = Mapping::new();
+                    entry.insert(key.clone(), value.clone());
+                    let at = self.append_point(node.end, indent);
+                    edits.push(Edit {
+                        start: at,
+                        end: at,
+                        lines: render(&Value::Mapping(entry), indent)?,
+                    });
+                }
+            }
+        }
+        for key in old.keys().filter(|key| !new.contains_key(*key)) {
+            let Some(child) = self.nodes.get(&join(path, key.as_str()?)) else {
+                continue;
+            };
+            // The first key of a `- key: value` item shares its line with the dash
+            if self.lines[child.start][..child.indent].contains('-') {
+                return None;
+            }
+            edits.push(self.removal(child));
+        }
+        Some(edits)
+    }
+
+    fn patch_sequence(
+        &self,
+        path: &str,
+        node: &Node,
+        indent: usize,
+        old: &[Value],
+        new: &[Value],
+    ) -> Option<Vec<Edit>> {
+        let mut edits = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            let item_path = format!("{}[{}]", path, i);
+            if i < old.len() && j < new.len() && old[i] == new[j] {
+                i += 1;
+                j += 1;
+            } else if old.len() - i > new.len() - j {
+                // Item removed
+                edits.push(self.removal(self.nodes.get(&item_path)?));
+                i += 1;
+            } else if new.len() - j > old.len() - i {
+                // Item inserted before the current one, or appended
+                let at = match self.nodes.get(&item_path) {
+                    Some(item) if i < old.len() => self.comment_start(item.start, item.indent),
+                    _ => self.append_point(node.end, indent),
+                };
+                edits.push(Edit {
+                    start: at,
+                    end: at,
+                    lines: render(&Value::Sequence(vec![new[j].clone()]), indent)?,
+                });
+                j += 1;
+            } else {
+                edits.extend(self.diff(&item_path, &old[i], &new[j])?);
+                i += 1;
+                j += 1;
+            }
+        }
+        Some(edits)
+    }
+
+    /// Remove a node together with the comment lines directly above it
+    fn removal(&self, node: &Node) -> Edit {
+        let mut start = self.comment_start(node.start, node.indent);
+        // Drop one of the blank lines that would otherwise end up doubled
+        let blank = |line: Option<&&str>| line.is_none_or(|line| line.trim().is_empty());
+        if start > 0 && blank(self.lines.get(start - 1)) && blank(self.lines.get(node.end)) {
+            start -= 1;
+        }
+        Edit {
+            start,
+            end: node.end,
+            lines: Vec::new(),
+        }
+    }
+
+    /// First line of the comment block directly above `start` at `indent` or deeper
+    fn comment_start(&self, mut start: usize, indent: usize) -> usize {
+        while start > 0 && self.is_comment_at(start - 1, indent) {
+            start -= 1;
+        }
+        start
+    }
+
+    /// Where to add a child to a block ending at `end`, after any trailing
+    /// comments that are indented deeper than the child and so belong to the block
+    fn append_point(&self, end: usize, indent: usize) -> usize {
+        let mut at = end;
+        let mut line = end;
+        while line < self.lines.len() {
+            if self.is_comment_at(line, indent + 1) {
+                at = line + 1;
+            } else if !self.lines[line].trim().is_empty() {
+                break;
+            }
+            line += 1;
+        }
+        at
+    }
+
+    fn is_comment_at(&self, line: usize, indent: usize) -> bool {
+        let text = self.lines[line];
+        let content = text.trim_start();
+        content.starts_with('#') && text.len() - content.len() >= indent
+    }
+
+    /// Re-serialize a whole entry or item, keeping the text around it
+    fn rewrite(&self, path: &str, new: &Value) -> Option<Vec<Edit>> {
+        let node = self.nodes.get(path)?;
+        let value = if node.is_entry {
+            let key = path.rsplit('.').next()?;
+            let mut entry = Mapping::new();
+            entry.insert(Value::String(key.to_string()), new.clone());
+            Value::Mapping(entry)
+        } else if path.ends_with(']') {
+            Value::Sequence(vec![new.clone()])
+        } else {
+            return None;
+        };
+
+        let mut lines = render(&value, node.indent)?;
+        // Keep a `- ` prefix shared with the parent item
+        let prefix = &self.lines[node.start][..node.indent];
+        lines[0] = format!("{}{}", prefix, &lines[0][node.indent..]);
+        Some(vec![Edit {
+            start: node.start,
+            end: node.end,
+            lines,
+        }])
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_)
+    )
+}
+
+fn render_scalar(value: &Value) -> Option<String> {
+    let text = serde_yaml::to_string(value).ok()?;
+    let text = text.trim_end();
+    (!text.contains('\n')).then(|| text.to_string())
+}
+
+/// Serialize `value` as block YAML indented by `indent` spaces
+fn render(value: &Value, indent: usize) -> Option<Vec<String>> {
+    let text = serde_yaml::to_string(value).ok()?;
+    let pad = " ".repeat(indent);
+    Some(
+        text.lines()
+            .map(|line| {
+                if line.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}{}", pad, line)
+                }
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Patch `text` to hold `new`, trusting only patches that read back as `new`
+    fn edit(text: &str, new: &str) -> Option<String> {
+        let old: Value = serde_yaml::from_str(text).unwrap();
+        let new: Value = serde_yaml::from_str(new).unwrap();
+        patch(text, &old, &new, |patched| {
+            serde_yaml::from_str::<Value>(patched).ok().as_ref() == Some(&new)
+        })
+    }
+
+    #[test]
+    fn keeps_comments_around_changed_values() {
+        let text = "# Header\nmacros:\n  # Flask\n  - key: \"1\"\n    interval_ms: 1000 # one second\n\n  # Skill\n  - key: e\n    interval_ms: 1500\n";
+        let new =
+            "macros:\n  - key: \"1\"\n    interval_ms: 900\n  - key: e\n    interval_ms: 1500\n";
+        assert_eq!(
+            edit(text, new).unwrap(),
+            "# Header\nmacros:\n  # Flask\n  - key: \"1\"\n    interval_ms: 900 # one second\n\n  # Skill\n  - key: e\n    interval_ms: 1500\n"
+        );
+    }
+
+    #[test]
+    fn adds_and_removes_entries() {
+        let text = "macros:\n  # Flask\n  - key: \"1\"\n  # Skill\n  - key: e\n# Trailing\n";
+        let new = "macros:\n  - key: e\n    enabled_by_default: true\nversion: 2\n";
+        assert_eq!(
+            edit(text, new).unwrap(),
+            "macros:\n  # Skill\n  - key: e\n    enabled_by_default: true\nversion: 2\n# Trailing\n"
+        );
+    }
+
+    #[test]
+    fn quoted_scalars_round_trip() {
+        let text = "a: \"1\"\nb: 'x: y'\nc: \"#not a comment\" # comment\n";
+        let new = "a: \"2\"\nb: 'x: z'\nc: \"#still not\"\n";
+        assert_eq!(
+            edit(text, new).unwrap(),
+            "a: '2'\nb: 'x: z'\nc: '#still not' # comment\n"
+        );
+    }
+
+    #[test]
+    fn flow_collections_are_rewritten_as_blocks() {
+        let text = "# Keys\nkeys: [F1, F2] # hotkeys\nmacros:\n  - {key: \"1\", interval_ms: 100}\n  - {key: e}\n";
+        let new = "keys: [F1, F3]\nmacros:\n  - {key: \"1\", interval_ms: 200}\n  - {key: e}\n";
+        assert_eq!(
+            edit(text, new).unwrap(),
+            "# Keys\nkeys:\n- F1\n- F3\nmacros:\n  - key: '1'\n    interval_ms: 200\n  - {key: e}\n"
+        );
+    }
+
+    #[test]
+    fn anchors_and_aliases_survive_or_fall_back() {
+        let text = "base: &base 100\nother: *base\nlast: 1\n";
+        // An unrelated change keeps the anchor and the alias
+        assert_eq!(
+            edit(text, "base: 100\nother: 100\nlast: 2\n").unwrap(),
+            "base: &base 100\nother: *base\nlast: 2\n"
+        );
+        // The alias alone changes
+        assert_eq!(
+            edit(text, "base: 100\nother: 300\nlast: 1\n").unwrap(),
+            "base: &base 100\nother: 300\nlast: 1\n"
+        );
+        // Changing the anchored value keeps the anchor
+        assert_eq!(
+            edit(text, "base: 200\nother: 200\nlast: 1\n").unwrap(),
+            "base: &base 200\nother: 200\nlast: 1\n"
+        );
+        // The alias would follow the anchored value, so the patch is refused
+        assert_eq!(edit(text, "base: 200\nother: 100\nlast: 1\n"), None);
+    }
+
+    #[test]
+    fn unsupported_documents_are_left_to_the_caller() {
+        assert_eq!(edit("- a\n- b\n", "- a\n- c\n"), None);
+        assert_eq!(edit("{a: 1}\n", "{a: 2}\n"), None);
+    }
+}
@@ -1,37 +1,81 @@
-import { useEffect, useState } from "react";
-import { Config, loadConfig, saveConfig, startMacroEngine, stopMacroEngine, validateConfig } from "@/lib/tauri";
+import { useEffect, useRef, useState } from "react";
+import { Config, ConfigDiff, KeyMacro, LoadedConfig, SaveConflict, addMacro, diffConfigs, isSaveConflict, loadConfig, onConfigChanged, reloadConfig, removeMacro, saveConfig, startMacroEngine, stopMacroEngine, updateMacro, validateConfig, validationErrorMessage } from "@/lib/tauri";
 import { MacroConfig } from "@/components/MacroConfig";
 import { StatusMonitor } from "@/components/StatusMonitor";
+import { ProfileManager } from "@/components/ProfileManager";
+import { ConfigDiffView } from "@/components/ConfigDiffView";
 import { LoadingScreen } from "@/components/LoadingScreen";
 import { Button } from "@/components/ui/button";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
 import { Tabs, TabsContent, TabsList, TabsTrigger } from "@/components/ui/tabs";
 import { useToast } from "@/hooks/use-toast";
 import { Toaster } from "@/components/ui/toaster";
-import { Play, Pause, Save, FolderOpen, Settings, Activity, Loader2 } from "lucide-react";
+import { ToastAction } from "@/components/ui/toast";
+import { Play, Pause, Save, FolderOpen, Settings, Activity, Loader2, Users } from "lucide-react";
 
 function App() {
   const [config, setConfig] = useState<Config>({ macros: [] });
+  // The configuration as last loaded or saved, and its changes under review
+  const [savedConfig, setSavedConfig] = useState<Config>({ macros: [] });
+  const [pendingDiff, setPendingDiff] = useState<ConfigDiff | null>(null);
+  // Version of the file the saved configuration matches, sent back when saving
+  const loadedVersion = useRef<number | null>(null);
+  // Current edits for event handlers registered once
+  const editing = useRef({ config, savedConfig });
+  editing.current = { config, savedConfig };
   const [isRunning, setIsRunning] = useState(false);
   const [isLoading, setIsLoading] = useState(false);
   const [isInitializing, setIsInitializing] = useState(true);
   const { toast } = useToast();
+  // Macros as the running engine has them, and the pending live edit
+  const appliedMacros = useRef<KeyMacro[]>([]);
+  const applyTimer = useRef<ReturnType<typeof setTimeout>>();
 
   useEffect(() => {
     loadConfiguration(true);
   }, []);
 
+  useEffect(() => {
+    const unlisten = onConfigChanged((changed) => {
+      // The engine hot-reloads the file either way
+      appliedMacros.current = changed.config.macros;
+      const { config, savedConfig } = editing.current;
+      if (JSON.stringify(config) !== JSON.stringify(savedConfig)) {
+        // Keep unsaved edits; saving them now reports the conflict
+        toast({
+          title: "Configuration Changed on Disk",
+          description: "The file was edited elsewhere while you have unsaved changes.",
+          variant: "destructive",
+          action: (
+            <ToastAction altText="Discard your changes and use the file on disk" onClick={() => showConfig(changed)}>
+              Use Theirs
+            </ToastAction>
+          ),
+        });
+        return;
+      }
+      showConfig(changed);
+      toast({
+        title: "Configuration Reloaded",
+        description: `Picked up ${changed.config.macros.length} macro(s) from disk`,
+      });
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   const loadConfiguration = async (isInitial = false) => {
     try {
       setIsLoading(true);
-      const loadedConfig = await loadConfig();
-      setConfig(loadedConfig);
+      const loaded = await loadConfig();
+      showConfig(loaded);
 
       // Only show toast for manual reloads, not initial load
       if (!isInitial) {
         toast({
           title: "Configuration Loaded",
-          description: `Loaded ${loadedConfig.macros.length} macro(s)`,
+          description: `Loaded ${loaded.config.macros.length} macro(s)`,
         });
       }
     } catch (error) {
@@ -48,10 +92,63 @@ function App() {
     }
   };
 
-  const saveConfiguration = async () => {
+  const keepTheirs = async () => {
+    showConfig(await reloadConfig());
+  };
+
+  // A configuration that came from disk, e.g. after switching profiles
+  const showConfig = (loaded: LoadedConfig) => {
+    setConfig(loaded.config);
+    setSavedConfig(loaded.config);
+    setPendingDiff(null);
+    appliedMacros.current = loaded.config.macros;
+    loadedVersion.current = loaded.version;
+  };
+
+  // Show what saving would change before writing it
+  const reviewChanges = async () => {
+    try {
+      const diff = await diffConfigs(savedConfig, config);
+      if (diff.macros.length === 0 && diff.settings.length === 0) {
+        toast({ title: "No Changes", description: "The configuration matches the saved file" });
+        return;
+      }
+      setPendingDiff(diff);
+    } catch (error) {
+      toast({
+        title: "Error",
+        description: `Failed to compare configuration: ${error}`,
+        variant: "destructive",
+      });
+    }
+  };
+
+  // Someone else changed the file: keep their version or overwrite it with ours
+  const showSaveConflict = (conflict: SaveConflict) => {
+    const changedAt = conflict.modified ? ` at ${new Date(conflict.modified).toLocaleTimeString()}` : "";
+    toast({
+      title: "Configuration Changed on Disk",
+      description: `${conflict.path} was edited elsewhere${changedAt}. Your changes were not saved.`,
+      variant: "destructive",
+      action: (
+        <div className="flex flex-col gap-2">
+          {conflict.theirs && (
+            <ToastAction altText="Use the file on disk" onClick={keepTheirs}>
+              Use Theirs
+            </ToastAction>
+          )}
+          <ToastAction altText="Overwrite the file with your changes" onClick={() => saveConfiguration(true)}>
+            Keep Mine
+          </ToastAction>
+        </div>
+      ),
+    });
+  };
+
+  const saveConfiguration = async (force = false) => {
     try {
       // Validate first
-      const validationError = await validateConfig(config);
+      const validationError = validationErrorMessage(await validateConfig(config));
       if (validationError) {
         toast({
           title: "Validation Error",
@@ -62,15 +159,21 @@ function App() {
       }
 
       setIsLoading(true);
-      await saveConfig(config);
+      loadedVersion.current = await saveConfig(config, loadedVersion.current, force);
+      setSavedConfig(config);
+      setPendingDiff(null);
       toast({
         title: "Configuration Saved",
         description: `Saved ${config.macros.length} macro(s) successfully`,
       });
     } catch (error) {
+      if (isSaveConflict(error)) {
+        showSaveConflict(error);
+        return;
+      }
       toast({
         title: "Error",
-        description: `Failed to save configuration: ${error}`,
+        description: `Failed to save configuration: ${(error as { message?: string }).message ?? error}`,
         variant: "destructive",
       });
     } finally {
@@ -78,10 +181,63 @@ function App() {
     }
   };
 
+  // Edits reach the running engine once typing pauses and the macro validates;
+  // saving persists them
+  const handleMacrosChange = (macros: KeyMacro[]) => {
+    const next = { ...config, macros };
+    setConfig(next);
+    setPendingDiff(null);
+    clearTimeout(applyTimer.current);
+    if (isRunning) {
+      applyTimer.current = setTimeout(() => applyToEngine(next), 600);
+    }
+  };
+
+  const applyToEngine = async (next: Config) => {
+    const previous = appliedMacros.current;
+    const applied = [...previous];
+    try {
+      const report = await validateConfig(next);
+      const invalid = new Set(
+        report.issues
+          .filter((issue) => issue.severity === "error")
+          .map((issue) => issue.path.match(/^macros\[(\d+)\]/)?.[1])
+      );
+
+      const ids = new Set(next.macros.map((macro) => macro.id));
+      for (const macro of previous.filter((macro) => !ids.has(macro.id))) {
+        await removeMacro(macro.id);
+        applied.splice(applied.findIndex((a) => a.id === macro.id), 1);
+      }
+      for (const [index, macro] of next.macros.entries()) {
+        // Half-filled entries wait until they validate
+        if (invalid.has(String(index))) {
+          continue;
+        }
+        const position = applied.findIndex((a) => a.id === macro.id);
+        if (position === -1) {
+          await addMacro(macro);
+          applied.push(macro);
+        } else if (JSON.stringify(applied[position]) !== JSON.stringify(macro)) {
+          await updateMacro(macro.id, macro);
+          applied[position] = macro;
+        }
+      }
+    } catch (error) {
+      toast({
+        title: "Not Applied",
+        description: `The running engine kept its previous settings: ${error}`,
+        variant: "destructive",
+      });
+    } finally {
+      appliedMacros.current = applied;
+    }
+  };
+
   const handleStart = async () => {
     try {
       // Validate before starting
-      const validationError = await validateConfig(config);
+      const validationError = validationErrorMessage(await validateConfig(config));
       if (validationError) {
         toast({
           title: "Validation Error",
@@ -93,6 +249,7 @@ function App() {
 
       setIsLoading(true);
       await startMacroEngine();
+      appliedMacros.current = config.macros;
       setIsRunning(true);
       toast({
         title: "Macro Engine Started",
@@ -112,6 +269,7 @@ function App() {
   const handleStop = async () => {
     try {
       setIsLoading(true);
+      clearTimeout(applyTimer.current);
       await stopMacroEngine();
       setIsRunning(false);
       toast({
@@ -179,8 +337,8 @@ function App() {
                 )}
               </Button>
               <Button
-                onClick={saveConfiguration}
-                disabled={isLoading || isRunning}
+                onClick={reviewChanges}
+                disabled={isLoading}
                 variant="outline"
                 size="lg"
               >
@@ -220,12 +378,26 @@ function App() {
                 Add at least one macro to start the engine
               </p>
             )}
+            {pendingDiff && (
+              <div className="mt-4 space-y-3 rounded-md border p-4">
+                <p className="text-sm font-medium">Saving will change:</p>
+                <ConfigDiffView diff={pendingDiff} />
+                <div className="flex gap-2">
+                  <Button size="sm" onClick={() => saveConfiguration()} disabled={isLoading}>
+                    Save
+                  </Button>
+                  <Button size="sm" variant="outline" onClick={() => setPendingDiff(null)}>
+                    Cancel
+                  </Button>
+                </div>
+              </div>
+            )}
           </CardContent>
         </Card>
 
         {/* Main Content */}
         <Tabs defaultValue="config" className="space-y-4">
-          <TabsList className="grid w-full grid-cols-2">
+          <TabsList className="grid w-full grid-cols-3">
             <TabsTrigger value="config">
               <Settings className="mr-2 h-4 w-4" />
               Configuration
@@ -234,6 +406,10 @@ function App() {
               <Activity className="mr-2 h-4 w-4" />
               Status Monitor
             </TabsTrigger>
+            <TabsTrigger value="profiles">
+              <Users className="mr-2 h-4 w-4" />
+              Profiles
+            </TabsTrigger>
           </TabsList>
 
           <TabsContent value="config" className="space-y-4">
@@ -245,7 +421,7 @@ function App() {
                 </CardDescription>
               </CardHeader>
               <CardContent>
-                <MacroConfig macros={config.macros} onChange={(macros) => setConfig({ macros })} />
+                <MacroConfig macros={config.macros} onChange={handleMacrosChange} />
               </CardContent>
             </Card>
           </TabsContent>
@@ -253,6 +429,10 @@ function App() {
           <TabsContent value="status">
             <StatusMonitor isRunning={isRunning} />
           </TabsContent>
+
+          <TabsContent value="profiles">
+            <ProfileManager isRunning={isRunning} onSwitch={showConfig} />
+          </TabsContent>
         </Tabs>
 
         {/* Footer */}
@@ -0,0 +1,55 @@
+import { ConfigDiff, FieldChange } from "@/lib/tauri";
+import { Badge } from "@/components/ui/badge";
+
+interface ConfigDiffViewProps {
+  diff: ConfigDiff;
+}
+
+// e.g. "interval 1000→900ms"
+function describeField(change: FieldChange): string {
+  const before = change.before ?? "none";
+  const after = change.after ?? "none";
+  const unit = change.after != null && change.unit ? change.unit : "";
+  return `${change.label} ${before}→${after}${unit}`;
+}
+
+const changeStyles = {
+  added: { label: "Added", className: "border-green-500 text-green-600" },
+  removed: { label: "Removed", className: "border-red-500 text-red-600" },
+  changed: { label: "Changed", className: "border-yellow-500 text-yellow-600" },
+};
+
+export function ConfigDiffView({ diff }: ConfigDiffViewProps) {
+  if (diff.macros.length === 0 && diff.settings.length === 0) {
+    return <p className="text-sm text-muted-foreground">No changes</p>;
+  }
+
+  return (
+    <ul className="space-y-2 text-sm">
+      {diff.macros.map((entry) => (
+        <li key={`${entry.change}-${entry.id}`} className="flex items-start gap-2">
+          <Badge variant="outline" className={changeStyles[entry.change].className}>
+            {changeStyles[entry.change].label}
+          </Badge>
+          <div>
+            <span className="font-medium">{entry.label}</span>
+            <span className="text-muted-foreground">
+              {" "}
+              {entry.change === "changed"
+                ? entry.fields.map(describeField).join(", ")
+                : entry.summary}
+            </span>
+          </div>
+        </li>
+      ))}
+      {diff.settings.map((setting) => (
+        <li key={setting.field} className="flex items-start gap-2">
+          <Badge variant="outline" className={changeStyles.changed.className}>
+            Setting
+          </Badge>
+          <span className="text-muted-foreground">{describeField(setting)}</span>
+        </li>
+      ))}
+    </ul>
+  );
+}
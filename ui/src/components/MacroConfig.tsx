@@ -1,6 +1,7 @@
 import { useState } from "react";
 import { KeyMacro } from "@/lib/tauri";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
+import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
 import { Slider } from "@/components/ui/slider";
 import { Switch } from "@/components/ui/switch";
@@ -22,6 +23,7 @@ export function MacroConfig({ macros, onChange }: MacroConfigProps) {
 
   const addMacro = () => {
     const newMacro: KeyMacro = {
+      id: `macro-${crypto.randomUUID().slice(0, 8)}`,
       action_type: "keyboard",
       key: "",
       interval_ms: 1000,
@@ -53,7 +55,7 @@ export function MacroConfig({ macros, onChange }: MacroConfigProps) {
       <div className="space-y-2">
         {macros.map((macro, index) => (
           <Card
-            key={index}
+            key={macro.id}
             className={editingIndex === index ? "border-primary" : ""}
           >
             <CardHeader className="pb-3">
@@ -65,15 +67,22 @@ export function MacroConfig({ macros, onChange }: MacroConfigProps) {
                     ) : (
                       <Mouse className="h-4 w-4 text-purple-500" />
                     )}
-                    Macro #{index + 1}
-                    {macro.action_type === "keyboard" && macro.key && macro.toggle_hotkey && (
+                    {macro.name || `Macro #${index + 1}`}
+                    {macro.game_action && macro.toggle_hotkey && (
+                      <span className="ml-2 text-sm font-normal text-muted-foreground">
+                        Game action: <kbd className="rounded bg-muted px-2 py-1">{macro.game_action}</kbd>
+                        {" → "}
+                        Hotkey: <kbd className="rounded bg-muted px-2 py-1">{macro.toggle_hotkey}</kbd>
+                      </span>
+                    )}
+                    {!macro.game_action && macro.action_type === "keyboard" && macro.key && macro.toggle_hotkey && (
                       <span className="ml-2 text-sm font-normal text-muted-foreground">
                         Key: <kbd className="rounded bg-muted px-2 py-1">{macro.key.toUpperCase()}</kbd>
                         {" → "}
                         Hotkey: <kbd className="rounded bg-muted px-2 py-1">{macro.toggle_hotkey}</kbd>
                       </span>
                     )}
-                    {macro.action_type === "mouse" && macro.mouse_button && macro.toggle_hotkey && (
+                    {!macro.game_action && macro.action_type === "mouse" && macro.mouse_button && macro.toggle_hotkey && (
                       <span className="ml-2 text-sm font-normal text-muted-foreground">
                         Button: <kbd className="rounded bg-muted px-2 py-1 capitalize">{macro.mouse_button}</kbd>
                         {" → "}
@@ -108,6 +117,26 @@ export function MacroConfig({ macros, onChange }: MacroConfigProps) {
 
             {editingIndex === index && (
               <CardContent className="space-y-6">
+                {/* Name, shown in logs and used by profiles that extend this one */}
+                <div className="space-y-2">
+                  <Label>Name</Label>
+                  <Input
+                    value={macro.name ?? ""}
+                    placeholder={macro.id}
+                    onChange={(e) => updateMacro(index, { name: e.target.value || null })}
+                  />
+                </div>
+
+                {/* Game action, pressed with whatever key the game binds to it */}
+                <div className="space-y-2">
+                  <Label>Game Action</Label>
+                  <Input
+                    value={macro.game_action ?? ""}
+                    placeholder="e.g. flask_1 or skill_slot_3 (overrides the key below)"
+                    onChange={(e) => updateMacro(index, { game_action: e.target.value || null })}
+                  />
+                </div>
+
                 {/* Action Type Selection */}
                 <div className="space-y-2">
                   <Label>Action Type</Label>
@@ -0,0 +1,309 @@
+import { useEffect, useState } from "react";
+import {
+  ConfigDiff,
+  ImportPreview,
+  LoadedConfig,
+  ProfileList,
+  Template,
+  TemplatePreview,
+  createProfile,
+  createProfileFromTemplate,
+  deleteProfile,
+  diffProfileCode,
+  exportProfile,
+  importProfileCode,
+  listProfiles,
+  listTemplates,
+  previewProfileCode,
+  previewTemplate,
+  switchProfile,
+  validationErrorMessage,
+} from "@/lib/tauri";
+import { ConfigDiffView } from "@/components/ConfigDiffView";
+import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import { Badge } from "@/components/ui/badge";
+import { useToast } from "@/hooks/use-toast";
+import { Copy, Download, Plus, Trash2, Users } from "lucide-react";
+
+// Start a new profile from the active one rather than a template
+const COPY_ACTIVE = "";
+
+interface ProfileManagerProps {
+  isRunning: boolean;
+  onSwitch: (loaded: LoadedConfig) => void;
+}
+
+export function ProfileManager({ isRunning, onSwitch }: ProfileManagerProps) {
+  const [profiles, setProfiles] = useState<ProfileList>({ active: "default", profiles: [] });
+  const [code, setCode] = useState("");
+  const [importName, setImportName] = useState("");
+  const [preview, setPreview] = useState<ImportPreview | null>(null);
+  const [diff, setDiff] = useState<ConfigDiff | null>(null);
+  const [templates, setTemplates] = useState<Template[]>([]);
+  const [newName, setNewName] = useState("");
+  const [templateName, setTemplateName] = useState(COPY_ACTIVE);
+  const [templatePreview, setTemplatePreview] = useState<TemplatePreview | null>(null);
+  const { toast } = useToast();
+
+  useEffect(() => {
+    refresh();
+    listTemplates()
+      .then(setTemplates)
+      .catch((error) => console.error("Failed to list templates:", error));
+  }, []);
+
+  const refresh = async () => {
+    try {
+      setProfiles(await listProfiles());
+    } catch (error) {
+      console.error("Failed to list profiles:", error);
+    }
+  };
+
+  const showError = (title: string, error: unknown) => {
+    toast({ title, description: `${error}`, variant: "destructive" });
+  };
+
+  const handleSwitch = async (name: string) => {
+    try {
+      onSwitch(await switchProfile(name));
+      await refresh();
+    } catch (error) {
+      showError("Switch Failed", error);
+    }
+  };
+
+  const handleDelete = async (name: string) => {
+    try {
+      await deleteProfile(name);
+      await refresh();
+    } catch (error) {
+      showError("Delete Failed", error);
+    }
+  };
+
+  const handleExport = async (name: string) => {
+    try {
+      await navigator.clipboard.writeText(await exportProfile(name));
+      toast({ title: "Share Code Copied", description: `Profile '${name}' is on the clipboard` });
+    } catch (error) {
+      showError("Export Failed", error);
+    }
+  };
+
+  const handlePickTemplate = async (name: string) => {
+    setTemplateName(name);
+    setTemplatePreview(null);
+    if (name === COPY_ACTIVE) {
+      return;
+    }
+    try {
+      setTemplatePreview(await previewTemplate(name));
+    } catch (error) {
+      showError("Invalid Template", error);
+    }
+  };
+
+  const handleCreate = async () => {
+    try {
+      if (templateName === COPY_ACTIVE) {
+        await createProfile(newName);
+      } else {
+        await createProfileFromTemplate(newName, templateName);
+      }
+      toast({ title: "Profile Created", description: `Switch to '${newName}' to use it` });
+      setNewName("");
+      await refresh();
+    } catch (error) {
+      showError("Create Failed", error);
+    }
+  };
+
+  const exists = profiles.profiles.includes(importName);
+
+  // Decode the code and, when it would replace a profile, what changes
+  const handlePreview = async () => {
+    try {
+      setPreview(await previewProfileCode(code.trim()));
+      setDiff(exists ? await diffProfileCode(code.trim(), importName) : null);
+    } catch (error) {
+      setPreview(null);
+      setDiff(null);
+      showError("Invalid Share Code", error);
+    }
+  };
+
+  const handleImport = async () => {
+    try {
+      await importProfileCode(code.trim(), importName, exists);
+      toast({ title: "Profile Imported", description: `Saved as '${importName}'` });
+      if (importName === profiles.active) {
+        onSwitch(await switchProfile(importName));
+      }
+      setCode("");
+      setPreview(null);
+      setDiff(null);
+      await refresh();
+    } catch (error) {
+      showError("Import Failed", error);
+    }
+  };
+
+  const previewError = preview ? validationErrorMessage(preview.validation) : null;
+
+  return (
+    <div className="space-y-4">
+      <Card>
+        <CardHeader>
+          <CardTitle className="flex items-center gap-2">
+            <Users className="h-5 w-5" />
+            Profiles
+          </CardTitle>
+          <CardDescription>Switch, share or delete profiles</CardDescription>
+        </CardHeader>
+        <CardContent className="space-y-2">
+          {profiles.profiles.map((name) => (
+            <div key={name} className="flex items-center gap-2">
+              <span className="font-medium">{name}</span>
+              {name === profiles.active && <Badge variant="outline">Active</Badge>}
+              <div className="ml-auto flex gap-2">
+                <Button
+                  size="sm"
+                  variant="outline"
+                  disabled={name === profiles.active}
+                  onClick={() => handleSwitch(name)}
+                >
+                  Switch
+                </Button>
+                <Button size="sm" variant="outline" onClick={() => handleExport(name)}>
+                  <Copy className="h-4 w-4" />
+                </Button>
+                <Button
+                  size="sm"
+                  variant="outline"
+                  disabled={name === profiles.active || name === "default" || isRunning}
+                  onClick={() => handleDelete(name)}
+                >
+                  <Trash2 className="h-4 w-4" />
+                </Button>
+              </div>
+            </div>
+          ))}
+        </CardContent>
+      </Card>
+
+      <Card>
+        <CardHeader>
+          <CardTitle className="flex items-center gap-2">
+            <Plus className="h-5 w-5" />
+            New Profile
+          </CardTitle>
+          <CardDescription>Start from a copy of the active profile or a built-in template</CardDescription>
+        </CardHeader>
+        <CardContent className="space-y-4">
+          <div className="grid grid-cols-2 gap-2 md:grid-cols-4">
+            {[{ name: COPY_ACTIVE, title: `Copy of ${profiles.active}` }, ...templates].map((template) => (
+              <button
+                key={template.name}
+                onClick={() => handlePickTemplate(template.name)}
+                className={`h-10 rounded-md border-2 text-xs font-medium transition-all ${
+                  templateName === template.name
+                    ? "border-primary bg-primary text-primary-foreground"
+                    : "border-border bg-background hover:border-primary/50"
+                }`}
+              >
+                {template.title}
+              </button>
+            ))}
+          </div>
+          {templatePreview && (
+            <div className="space-y-1 rounded-md border p-4 text-sm">
+              <p>{templatePreview.template.description}</p>
+              <ul className="text-muted-foreground">
+                {templatePreview.config.macros.map((macro) => (
+                  <li key={macro.id}>
+                    {macro.name ?? macro.id}: {macro.game_action ?? macro.mouse_button ?? macro.key} every{" "}
+                    {macro.interval_ms}ms, toggled with {macro.toggle_hotkey}
+                  </li>
+                ))}
+              </ul>
+            </div>
+          )}
+          <div className="space-y-2">
+            <Label htmlFor="new-profile-name">Profile name</Label>
+            <Input id="new-profile-name" value={newName} onChange={(e) => setNewName(e.target.value)} />
+          </div>
+          <Button disabled={!newName || profiles.profiles.includes(newName)} onClick={handleCreate}>
+            Create
+          </Button>
+        </CardContent>
+      </Card>
+
+      <Card>
+        <CardHeader>
+          <CardTitle className="flex items-center gap-2">
+            <Download className="h-5 w-5" />
+            Import Share Code
+          </CardTitle>
+          <CardDescription>Review what a code contains before saving it as a profile</CardDescription>
+        </CardHeader>
+        <CardContent className="space-y-4">
+          <div className="space-y-2">
+            <Label htmlFor="share-code">Share code</Label>
+            <textarea
+              id="share-code"
+              className="flex min-h-20 w-full rounded-md border border-input bg-background px-3 py-2 text-sm font-mono"
+              placeholder="poe2mb:..."
+              value={code}
+              onChange={(e) => {
+                setCode(e.target.value);
+                setPreview(null);
+                setDiff(null);
+              }}
+            />
+          </div>
+          <div className="space-y-2">
+            <Label htmlFor="import-name">Profile name</Label>
+            <Input
+              id="import-name"
+              value={importName}
+              onChange={(e) => {
+                setImportName(e.target.value);
+                setPreview(null);
+                setDiff(null);
+              }}
+            />
+          </div>
+          <Button variant="outline" disabled={!code.trim() || !importName} onClick={handlePreview}>
+            Preview
+          </Button>
+
+          {preview && (
+            <div className="space-y-2 rounded-md border p-4">
+              <p className="text-sm">
+                {preview.config.macros.length} macro(s)
+                {preview.migration &&
+                  `, upgraded from version ${preview.migration.from_version}`}
+              </p>
+              {previewError && <p className="text-sm text-destructive whitespace-pre-line">{previewError}</p>}
+              {diff ? (
+                <>
+                  <p className="text-sm font-medium">Changes to '{importName}'</p>
+                  <ConfigDiffView diff={diff} />
+                </>
+              ) : (
+                <p className="text-sm text-muted-foreground">Creates a new profile '{importName}'</p>
+              )}
+              <Button disabled={!!previewError} onClick={handleImport}>
+                {exists ? `Replace '${importName}'` : "Import"}
+              </Button>
+            </div>
+          )}
+        </CardContent>
+      </Card>
+    </div>
+  );
+}
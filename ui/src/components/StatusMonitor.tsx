@@ -1,5 +1,5 @@
 import { useEffect, useState } from "react";
-import { MacroStatus, getMacroStatus, toggleMacro } from "@/lib/tauri";
+import { MacroStatus, SessionStatus, getMacroStatus, getSessionStatus, resetMacroErrors, toggleMacro } from "@/lib/tauri";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
 import { Switch } from "@/components/ui/switch";
 import { Badge } from "@/components/ui/badge";
@@ -11,6 +11,7 @@ interface StatusMonitorProps {
 
 export function StatusMonitor({ isRunning }: StatusMonitorProps) {
   const [statuses, setStatuses] = useState<MacroStatus[]>([]);
+  const [session, setSession] = useState<SessionStatus | null>(null);
 
   useEffect(() => {
     if (!isRunning) {
@@ -28,16 +29,21 @@ export function StatusMonitor({ isRunning }: StatusMonitorProps) {
 
   const fetchStatus = async () => {
     try {
-      const status = await getMacroStatus();
+      const [status, sessionStatus] = await Promise.all([getMacroStatus(), getSessionStatus()]);
       setStatuses(status);
+      setSession(sessionStatus);
     } catch (error) {
       console.error("Failed to fetch macro status:", error);
     }
   };
 
-  const handleToggle = async (index: number) => {
+  const handleToggle = async (status: MacroStatus) => {
     try {
-      await toggleMacro(index);
+      // Toggling a stopped macro clears its failure state first
+      if (status.errored) {
+        await resetMacroErrors(status.id);
+      }
+      await toggleMacro(status.id);
       await fetchStatus();
     } catch (error) {
       console.error("Failed to toggle macro:", error);
@@ -73,13 +79,18 @@ export function StatusMonitor({ isRunning }: StatusMonitorProps) {
             {statuses.filter((s) => s.enabled).length} / {statuses.length} Active
           </Badge>
         </CardTitle>
-        <CardDescription>Real-time macro status</CardDescription>
+        <CardDescription>
+          Real-time macro status
+          {session?.limit_reached && " · Session limit reached, engine stopped"}
+          {session?.running && session.remaining_secs != null &&
+            ` · ${Math.ceil(session.remaining_secs / 60)} min left in session`}
+        </CardDescription>
       </CardHeader>
       <CardContent>
         <div className="space-y-3">
           {statuses.map((status) => (
             <div
-              key={status.index}
+              key={status.id}
               className="flex items-center justify-between rounded-lg border p-3"
             >
               <div className="flex items-center gap-3">
@@ -95,26 +106,33 @@ export function StatusMonitor({ isRunning }: StatusMonitorProps) {
                 )}
                 <div>
                   <p className="text-sm font-medium">
-                    Macro #{status.index + 1}
+                    {status.name ?? status.id}
                   </p>
                   {status.action_type === "keyboard" ? (
                     <p className="text-xs text-muted-foreground">
                       Key: <kbd className="rounded bg-muted px-1.5 py-0.5">{status.key.toUpperCase()}</kbd>
+                      {status.game_action && ` (${status.game_action})`}
                       {" → "}
                       Hotkey: <kbd className="rounded bg-muted px-1.5 py-0.5">{status.toggle_hotkey}</kbd>
                     </p>
                   ) : (
                     <p className="text-xs text-muted-foreground">
                       Button: <kbd className="rounded bg-muted px-1.5 py-0.5 capitalize">{status.mouse_button}</kbd>
+                      {status.game_action && ` (${status.game_action})`}
                       {" → "}
                       Hotkey: <kbd className="rounded bg-muted px-1.5 py-0.5">{status.toggle_hotkey}</kbd>
                     </p>
                   )}
+                  {status.errored && status.last_error && (
+                    <p className="text-xs text-destructive">Error: {status.last_error}</p>
+                  )}
                 </div>
               </div>
               <div className="flex items-center gap-2">
-                <Badge variant={status.enabled ? "default" : "secondary"}>
-                  {status.enabled ? (
+                <Badge variant={status.errored ? "destructive" : status.enabled ? "default" : "secondary"}>
+                  {status.errored ? (
+                    "Errored"
+                  ) : status.enabled ? (
                     <>
                       <Play className="mr-1 h-3 w-3" />
                       Running
@@ -128,7 +146,7 @@ export function StatusMonitor({ isRunning }: StatusMonitorProps) {
                 </Badge>
                 <Switch
                   checked={status.enabled}
-                  onCheckedChange={() => handleToggle(status.index)}
+                  onCheckedChange={() => handleToggle(status)}
                 />
               </div>
             </div>
@@ -1,11 +1,15 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 
 export type ActionType = "keyboard" | "mouse";
 export type MouseButton = "left" | "right" | "middle";
 
 export interface KeyMacro {
+  id: string;
+  name?: string | null;
   action_type: ActionType;
   key: string;
+  game_action?: string | null;
   mouse_button?: MouseButton;
   interval_ms: number;
   random_variance_ms: number;
@@ -13,27 +17,176 @@ export interface KeyMacro {
   enabled_by_default: boolean;
 }
 
+export interface SessionConfig {
+  max_runtime_minutes?: number | null;
+  break_reminder_minutes?: number | null;
+}
+
 export interface Config {
+  version?: number;
   macros: KeyMacro[];
+  session?: SessionConfig;
+  active_profile?: string | null;
+  profile_cycle_hotkey?: string | null;
+  game_config?: string | null;
+}
+
+export interface Keybind {
+  action: string;
+  key: string;
+  modified: boolean;
+}
+
+export interface GameKeybinds {
+  path: string;
+  binds: Keybind[];
+}
+
+export interface ProfileList {
+  active: string;
+  profiles: string[];
 }
 
 export interface MacroStatus {
-  index: number;
+  id: string;
+  name?: string | null;
   enabled: boolean;
   action_type: ActionType;
   key: string;
+  game_action?: string | null;
   mouse_button?: MouseButton;
   toggle_hotkey: string;
+  errored: boolean;
+  last_error?: string | null;
+}
+
+export interface SessionStatus {
+  running: boolean;
+  elapsed_secs: number;
+  remaining_secs?: number | null;
+  next_break_secs?: number | null;
+  limit_reached: boolean;
+}
+
+export type ConfigSource = "argument" | "environment" | "portable" | "config_dir";
+
+export interface ConfigLocation {
+  path: string;
+  source: ConfigSource;
+}
+
+export interface MigrationReport {
+  from_version: number;
+  to_version: number;
+  changes: string[];
+  backup_path?: string | null;
+}
+
+export interface LoadReport {
+  migration?: MigrationReport | null;
+  restored_backup?: string | null;
+}
+
+export type Severity = "error" | "warning";
+
+export interface ValidationIssue {
+  path: string;
+  severity: Severity;
+  code: string;
+  message: string;
+  source?: string | null;
+}
+
+export interface ValidationReport {
+  issues: ValidationIssue[];
+}
+
+export interface ImportPreview {
+  config: Config;
+  migration?: MigrationReport | null;
+  validation: ValidationReport;
+}
+
+export interface FieldChange {
+  field: string;
+  label: string;
+  before?: string | null;
+  after?: string | null;
+  unit?: string;
+}
+
+export interface MacroDiff {
+  id: string;
+  label: string;
+  change: "added" | "removed" | "changed";
+  fields: FieldChange[];
+  summary?: string;
+}
+
+export interface ConfigDiff {
+  macros: MacroDiff[];
+  settings: FieldChange[];
+}
+
+// A configuration and the version of the file it came from, to send back when saving
+export interface LoadedConfig {
+  config: Config;
+  version: number | null;
+}
+
+export interface SaveConflict {
+  kind: "conflict";
+  path: string;
+  modified: string | null;
+  ours: Config;
+  theirs: Config | null;
+}
+
+export interface Template {
+  name: string;
+  title: string;
+  description: string;
+  content: string;
+}
+
+export interface TemplatePreview {
+  template: Template;
+  config: Config;
 }
 
 // Load configuration from file
-export async function loadConfig(): Promise<Config> {
-  return await invoke<Config>("load_config");
+export async function loadConfig(): Promise<LoadedConfig> {
+  return await invoke<LoadedConfig>("load_config");
+}
+
+// Get the resolved config file path and where it came from
+export async function getConfigLocation(): Promise<ConfigLocation> {
+  return await invoke<ConfigLocation>("get_config_location");
+}
+
+// Get the schema upgrade or backup restore that happened while loading, if any
+export async function getLoadReport(): Promise<LoadReport | null> {
+  return await invoke<LoadReport | null>("get_load_report");
+}
+
+// Read the configuration from disk again, dropping the cached copy
+export async function reloadConfig(): Promise<LoadedConfig> {
+  return await invoke<LoadedConfig>("reload_config");
+}
+
+// Save configuration to file, returning the new version; rejects with a SaveConflict when the file no longer matches the version it was loaded with, unless forced
+export async function saveConfig(config: Config, version: number | null, force = false): Promise<number | null> {
+  return await invoke<number | null>("save_config", { config, version, force });
 }
 
-// Save configuration to file
-export async function saveConfig(config: Config): Promise<void> {
-  await invoke("save_config", { config });
+// Whether a saveConfig error means the file was changed by another program
+export function isSaveConflict(error: unknown): error is SaveConflict {
+  return typeof error === "object" && error !== null && (error as SaveConflict).kind === "conflict";
+}
+
+// Get the JSON Schema of the config file format
+export async function getConfigSchema(): Promise<Record<string, unknown>> {
+  return await invoke<Record<string, unknown>>("get_config_schema");
 }
 
 // Start macro engine
@@ -52,12 +205,119 @@ export async function getMacroStatus(): Promise<MacroStatus[]> {
 }
 
 // Toggle specific macro
-export async function toggleMacro(index: number): Promise<void> {
-  await invoke("toggle_macro", { index });
+export async function toggleMacro(id: string): Promise<void> {
+  await invoke("toggle_macro", { id });
+}
+
+// Replace a macro of the running engine; returns the running config
+export async function updateMacro(id: string, macroConfig: KeyMacro): Promise<Config> {
+  return await invoke<Config>("update_macro", { id, macroConfig });
+}
+
+// Add a macro to the running engine; returns the running config
+export async function addMacro(macroConfig: KeyMacro): Promise<Config> {
+  return await invoke<Config>("add_macro", { macroConfig });
+}
+
+// Remove a macro from the running engine; returns the running config
+export async function removeMacro(id: string): Promise<Config> {
+  return await invoke<Config>("remove_macro", { id });
+}
+
+// Get session runtime and limits
+export async function getSessionStatus(): Promise<SessionStatus> {
+  return await invoke<SessionStatus>("get_session_status");
+}
+
+// List profiles and the active one
+export async function listProfiles(): Promise<ProfileList> {
+  return await invoke<ProfileList>("list_profiles");
+}
+
+// Switch to a profile, returning its configuration
+export async function switchProfile(name: string): Promise<LoadedConfig> {
+  return await invoke<LoadedConfig>("switch_profile", { name });
+}
+
+// Create a profile as a copy of the active one
+export async function createProfile(name: string): Promise<void> {
+  await invoke("create_profile", { name });
+}
+
+// Delete an inactive profile
+export async function deleteProfile(name: string): Promise<void> {
+  await invoke("delete_profile", { name });
+}
+
+// Get the share code of a profile, the active one when name is omitted
+export async function exportProfile(name?: string): Promise<string> {
+  return await invoke<string>("export_profile", { name: name ?? null });
+}
+
+// Decode a share code to review it before importing
+export async function previewProfileCode(code: string): Promise<ImportPreview> {
+  return await invoke<ImportPreview>("preview_profile_code", { code });
+}
+
+// Import a share code as a profile, overwriting an existing one only with replace
+export async function importProfileCode(
+  code: string,
+  name: string,
+  replace = false,
+): Promise<ImportPreview> {
+  return await invoke<ImportPreview>("import_profile_code", { code, name, replace });
+}
+
+// Compare two configs by macro and setting, e.g. before saving
+export async function diffConfigs(before: Config, after: Config): Promise<ConfigDiff> {
+  return await invoke<ConfigDiff>("diff_configs", { before, after });
+}
+
+// What importing a share code would change in a profile, the active one when name is omitted
+export async function diffProfileCode(code: string, name?: string): Promise<ConfigDiff> {
+  return await invoke<ConfigDiff>("diff_profile_code", { code, name: name ?? null });
+}
+
+// Built-in templates to start a profile from
+export async function listTemplates(): Promise<Template[]> {
+  return await invoke<Template[]>("list_templates");
 }
 
-// Validate configuration
-export async function validateConfig(config: Config): Promise<string | null> {
-  return await invoke<string | null>("validate_config", { config });
+// A template and the config it produces
+export async function previewTemplate(name: string): Promise<TemplatePreview> {
+  return await invoke<TemplatePreview>("preview_template", { name });
 }
 
+// Create a new profile from a template
+export async function createProfileFromTemplate(name: string, template: string): Promise<void> {
+  await invoke("create_profile_from_template", { name, template });
+}
+
+// Reset the failure state of one macro, or of all macros when id is omitted
+export async function resetMacroErrors(id?: string): Promise<void> {
+  await invoke("reset_macro_errors", { id: id ?? null });
+}
+
+// Validate configuration, returning every issue found
+export async function validateConfig(config: Config): Promise<ValidationReport> {
+  return await invoke<ValidationReport>("validate_config", { config });
+}
+
+// The game's own keybinds, or null when its config file can't be found
+export async function getGameKeybinds(): Promise<GameKeybinds | null> {
+  return await invoke<GameKeybinds | null>("get_game_keybinds");
+}
+
+// Summarize the errors in a report, or null when the config is valid
+export function validationErrorMessage(report: ValidationReport): string | null {
+  const errors = report.issues.filter((issue) => issue.severity === "error");
+  if (errors.length === 0) {
+    return null;
+  }
+  return errors.map((issue) => issue.message).join("\n");
+}
+
+// Fired when the config file changed on disk and was hot-reloaded
+export function onConfigChanged(handler: (loaded: LoadedConfig) => void): Promise<UnlistenFn> {
+  return listen<LoadedConfig>("config-changed", (event) => handler(event.payload));
+}